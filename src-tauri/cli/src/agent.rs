@@ -0,0 +1,456 @@
+// 解锁代理（类似 ssh-agent / gpg-agent）：后台进程保存解锁后的保险库，通过 Unix socket 为命令行提供服务，
+// 这样只在启动代理时运行一次 Argon2。只接受当前用户的连接，空闲超过 TTL 或收到 lock 请求时清零密钥并退出。
+// 协议：每行一个 JSON 请求 {"op", "params"}，每行一个 JSON 响应 {"ok": ...} 或 {"error": {code, details}}
+use crate::session::{self, Session};
+use crate::GlobalArgs;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use twopass_core::unix_socket::{bind_private, peer_is_current_user};
+use twopass_core::{Error, PasswordEntry, PasswordGroup, SecretString, Vault};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+const SOCKET_NAME: &str = "agent.sock";
+
+// 单个客户端最长等待时间，避免卡住的连接阻塞其它请求
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+const TTL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", content = "params", rename_all = "snake_case")]
+pub enum Request {
+    Status,
+    Lock,
+    Data,
+    AddEntry {
+        entry: PasswordEntry,
+    },
+    UpdateEntry {
+        entry: PasswordEntry,
+    },
+    DeleteEntry {
+        id: String,
+    },
+    ImportChromeCsv {
+        content: String,
+    },
+    ImportEncrypted {
+        content: String,
+        password: SecretString,
+    },
+    Export,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Response {
+    Ok(Value),
+    Error(Error),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Status {
+    pub version: u32,
+    pub pid: u32,
+    pub vault: PathBuf,
+    pub read_only: bool,
+    pub expires_in: Option<u64>, // 距离自动锁定的秒数，None 表示不会自动锁定
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VaultData {
+    pub entries: Vec<PasswordEntry>,
+    pub groups: Vec<PasswordGroup>,
+}
+
+/// --agent-socket / TWOPASS_AGENT_SOCK，默认是数据目录下的 agent.sock
+pub fn socket_path(global: &GlobalArgs, data_dir: &Path) -> PathBuf {
+    global
+        .agent_socket
+        .clone()
+        .unwrap_or_else(|| data_dir.join(SOCKET_NAME))
+}
+
+pub struct AgentClient {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl AgentClient {
+    pub fn connect(socket: &Path) -> Result<Self, Error> {
+        let writer = UnixStream::connect(socket)
+            .map_err(|e| Error::io("Failed to connect to the agent", e))?;
+        let reader = writer
+            .try_clone()
+            .map_err(|e| Error::io("Failed to connect to the agent", e))?;
+        Ok(Self {
+            reader: BufReader::new(reader),
+            writer,
+        })
+    }
+
+    pub fn call<T: DeserializeOwned>(&mut self, request: &Request) -> Result<T, Error> {
+        let talk_error = |e| Error::io("Failed to talk to the agent", e);
+        let mut line = serde_json::to_string(request).unwrap();
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).map_err(talk_error)?;
+
+        let mut response = String::new();
+        if self.reader.read_line(&mut response).map_err(talk_error)? == 0 {
+            return Err(Error::io(
+                "Failed to talk to the agent",
+                "connection closed",
+            ));
+        }
+        let response: Response = serde_json::from_str(&response)
+            .map_err(|e| Error::io("Invalid response from the agent", e))?;
+        match response {
+            Response::Ok(value) => serde_json::from_value(value)
+                .map_err(|e| Error::io("Invalid response from the agent", e)),
+            Response::Error(e) => Err(e),
+        }
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// 有代理在运行且解锁的正是 vault_path 时，通过代理访问保险库。
+/// 修改类命令遇到只读代理时返回 None，直接读写解锁
+pub fn session_for(
+    global: &GlobalArgs,
+    data_dir: &Path,
+    vault_path: &Path,
+    read_only: bool,
+) -> Option<Session> {
+    if global.no_agent {
+        return None;
+    }
+    let mut client = AgentClient::connect(&socket_path(global, data_dir)).ok()?;
+    let status: Status = client.call(&Request::Status).ok()?;
+    if status.version != PROTOCOL_VERSION
+        || !same_file(&status.vault, vault_path)
+        || (status.read_only && !read_only)
+    {
+        return None;
+    }
+    let data = client.call(&Request::Data).ok()?;
+    Some(Session::Agent { client, data })
+}
+
+struct Agent {
+    vault: Vault,
+    vault_path: PathBuf,
+    socket: PathBuf,
+    ttl: Option<Duration>,
+    last_used: Instant,
+}
+
+impl Agent {
+    fn expires_in(&self) -> Option<Duration> {
+        self.ttl
+            .map(|ttl| ttl.saturating_sub(self.last_used.elapsed()))
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            version: PROTOCOL_VERSION,
+            pid: std::process::id(),
+            vault: self.vault_path.clone(),
+            read_only: self.vault.is_read_only(),
+            expires_in: self.expires_in().map(|d| d.as_secs()),
+        }
+    }
+
+    fn handle(&mut self, request: Request) -> Result<Value, Error> {
+        // 桌面应用或其它进程修改过保险库时先重新读取
+        if self.vault.changed_externally() {
+            self.vault.reload()?;
+        }
+        if let Request::Status = request {
+            return Ok(to_value(self.status()));
+        }
+        let vault = &mut self.vault;
+        let value = match request {
+            Request::Status | Request::Lock => Value::Null,
+            Request::Data => to_value(VaultData {
+                entries: vault.entries()?.to_vec(),
+                groups: vault.groups()?.to_vec(),
+            }),
+            Request::AddEntry { entry } => to_value(vault.add_entry(entry)?),
+            Request::UpdateEntry { entry } => to_value(vault.update_entry(entry)?),
+            Request::DeleteEntry { id } => to_value(vault.delete_entry(&id)?),
+            Request::ImportChromeCsv { content } => to_value(vault.import_chrome_csv(&content)?),
+            Request::ImportEncrypted { content, password } => {
+                to_value(vault.import_encrypted(&content, password)?)
+            }
+            Request::Export => to_value(vault.export()?),
+        };
+        Ok(value)
+    }
+
+    // 清零密钥和解密后的数据，删除 socket 后退出进程
+    fn shutdown(&mut self, reason: &str) -> ! {
+        self.vault.lock();
+        let _ = fs::remove_file(&self.socket);
+        eprintln!("🔒 Agent locked ({})", reason);
+        std::process::exit(0);
+    }
+}
+
+fn to_value(value: impl Serialize) -> Value {
+    serde_json::to_value(value).unwrap()
+}
+
+// 禁止 core dump 和同一用户的其它进程 ptrace 读取内存，和 ssh-agent 的做法相同
+fn harden_process() {
+    #[cfg(target_os = "linux")]
+    // SAFETY: PR_SET_DUMPABLE 只修改当前进程的属性
+    unsafe {
+        libc::prctl(libc::PR_SET_DUMPABLE, 0);
+    }
+}
+
+/// 在当前进程中运行代理，直到被锁定。开始监听后调用 ready
+pub fn serve(
+    vault: Vault,
+    vault_path: PathBuf,
+    socket: PathBuf,
+    ttl: Option<Duration>,
+    ready: impl FnOnce(&Status),
+) -> Result<(), Error> {
+    harden_process();
+    let listener = bind_private(&socket)?.ok_or_else(|| {
+        Error::invalid_input(
+            "agent",
+            &format!("an agent is already running on {}", socket.display()),
+        )
+    })?;
+
+    let agent = Arc::new(Mutex::new(Agent {
+        vault,
+        vault_path,
+        socket,
+        ttl,
+        last_used: Instant::now(),
+    }));
+    ready(&agent.lock().unwrap().status());
+
+    // 空闲超过 TTL 后自动锁定
+    let ttl_agent = Arc::clone(&agent);
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TTL_CHECK_INTERVAL);
+        let mut agent = ttl_agent.lock().unwrap();
+        if agent.expires_in().is_some_and(|d| d.is_zero()) {
+            agent.shutdown("idle timeout");
+        }
+    });
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        match peer_is_current_user(&stream) {
+            Ok(true) => {}
+            Ok(false) => {
+                eprintln!("⛔ Rejected agent connection from another user");
+                continue;
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to check agent peer: {}", e);
+                continue;
+            }
+        }
+        // 每个连接一个线程，等待确认提示等的客户端不会阻塞其它 2pass 命令；共享状态由 Mutex 保护
+        let agent = Arc::clone(&agent);
+        std::thread::spawn(move || {
+            let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+            serve_client(&agent, stream);
+        });
+    }
+    Ok(())
+}
+
+// 逐行处理一个客户端的请求，直到连接关闭
+fn serve_client(agent: &Mutex<Agent>, stream: UnixStream) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { return };
+        let mut agent = agent.lock().unwrap();
+        agent.last_used = Instant::now();
+
+        let (response, lock) = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let lock = matches!(request, Request::Lock);
+                (agent.handle(request), lock)
+            }
+            Err(e) => (Err(Error::invalid_input("request", &e.to_string())), false),
+        };
+        let response = match response {
+            Ok(value) => Response::Ok(value),
+            Err(e) => Response::Error(e),
+        };
+        let mut out = serde_json::to_string(&response).unwrap();
+        out.push('\n');
+        let _ = writer.write_all(out.as_bytes());
+        if lock {
+            agent.shutdown("lock requested");
+        }
+    }
+}
+
+/// 解锁保险库并在后台启动代理：提示输入主密码后启动 `2pass agent serve`，通过管道传递主密码，
+/// 等它开始监听（或解锁失败）后返回
+pub fn start(
+    global: &GlobalArgs,
+    ttl: u64,
+    read_only: bool,
+    foreground: bool,
+) -> Result<(), Error> {
+    let data_dir = session::data_dir(global)?;
+    let vault_path = session::vault_path(global, &data_dir);
+    let socket = socket_path(global, &data_dir);
+    if UnixStream::connect(&socket).is_ok() {
+        return Err(Error::invalid_input(
+            "agent",
+            &format!("an agent is already running on {}", socket.display()),
+        ));
+    }
+
+    if foreground {
+        let vault = session::unlock(global, &data_dir, &vault_path, read_only)?;
+        return serve(vault, vault_path, socket.clone(), ttl_duration(ttl), |_| {
+            eprintln!("🔓 Agent listening on {}", socket.display());
+        });
+    }
+
+    let password = session::read_master_password(global)?;
+    let exe =
+        std::env::current_exe().map_err(|e| Error::io("Failed to locate the executable", e))?;
+    let mut command = Command::new(exe);
+    command
+        .arg("--vault")
+        .arg(&vault_path)
+        .arg("--data-dir")
+        .arg(&data_dir)
+        .arg("--agent-socket")
+        .arg(&socket)
+        .arg("--password-stdin");
+    if let Some(key_file) = &global.key_file {
+        let key_file = std::path::absolute(key_file)
+            .map_err(|e| Error::invalid_input("key-file", &e.to_string()))?;
+        command.arg("--key-file").arg(key_file);
+    }
+    command.args(["agent", "serve", "--ttl", &ttl.to_string()]);
+    if read_only {
+        command.arg("--read-only");
+    }
+    // 新的进程组：关闭终端时不会随 shell 一起收到 SIGHUP
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| Error::io("Failed to start the agent", e))?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    // 一次分配好容量，拼接时不会重新分配留下副本；SecretString 在 drop 时清零
+    let mut input = String::with_capacity(password.len() + 1);
+    input.push_str(&password);
+    input.push('\n');
+    let input = SecretString::new(input);
+    stdin
+        .write_all(input.as_bytes())
+        .map_err(|e| Error::io("Failed to start the agent", e))?;
+    drop(stdin);
+
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut line)
+        .map_err(|e| Error::io("Failed to start the agent", e))?;
+    let response: Response = serde_json::from_str(&line)
+        .map_err(|_| Error::io("Failed to start the agent", "the agent exited unexpectedly"))?;
+    let status: Status = match response {
+        Response::Ok(value) => serde_json::from_value(value)
+            .map_err(|e| Error::io("Invalid response from the agent", e))?,
+        Response::Error(e) => return Err(e),
+    };
+    print_status(global, &status, &socket);
+    Ok(())
+}
+
+/// 后台代理进程：从标准输入读取主密码解锁后开始监听，把结果写成一行响应告诉 start
+pub fn serve_background(global: &GlobalArgs, ttl: u64, read_only: bool) -> Result<(), Error> {
+    let report = |response: &Response| {
+        println!("{}", serde_json::to_string(response).unwrap());
+    };
+    let data_dir = session::data_dir(global)?;
+    let vault_path = session::vault_path(global, &data_dir);
+    let socket = socket_path(global, &data_dir);
+    let result = session::unlock(global, &data_dir, &vault_path, read_only).and_then(|vault| {
+        serve(vault, vault_path, socket, ttl_duration(ttl), |status| {
+            report(&Response::Ok(to_value(status)))
+        })
+    });
+    if let Err(e) = result {
+        report(&Response::Error(e));
+    }
+    Ok(())
+}
+
+fn ttl_duration(ttl: u64) -> Option<Duration> {
+    (ttl > 0).then(|| Duration::from_secs(ttl))
+}
+
+fn connect(global: &GlobalArgs) -> Result<(AgentClient, PathBuf), Error> {
+    let socket = socket_path(global, &session::data_dir(global)?);
+    if !socket.exists() {
+        return Err(Error::io("No agent is running", socket.display()));
+    }
+    Ok((AgentClient::connect(&socket)?, socket))
+}
+
+fn print_status(global: &GlobalArgs, status: &Status, socket: &Path) {
+    if global.json {
+        return crate::output::print_json(status);
+    }
+    println!("Agent running (pid {}) on {}", status.pid, socket.display());
+    println!(
+        "Vault: {}{}",
+        status.vault.display(),
+        if status.read_only { " (read-only)" } else { "" }
+    );
+    match status.expires_in {
+        Some(secs) => println!("Locks after {}s without use", secs),
+        None => println!("Stays unlocked until `2pass agent lock`"),
+    }
+}
+
+pub fn status(global: &GlobalArgs) -> Result<(), Error> {
+    let (mut client, socket) = connect(global)?;
+    let status = client.call(&Request::Status)?;
+    print_status(global, &status, &socket);
+    Ok(())
+}
+
+pub fn lock(global: &GlobalArgs) -> Result<(), Error> {
+    let (mut client, _) = connect(global)?;
+    client.call::<Value>(&Request::Lock)?;
+    if !global.json {
+        println!("Agent locked");
+    }
+    Ok(())
+}
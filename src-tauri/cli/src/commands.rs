@@ -0,0 +1,421 @@
+// 各个子命令：读取类命令只读解锁，修改类命令读写解锁后通过 Vault 的方法写回，和桌面应用的命令走同一套逻辑
+use crate::output::{self, print_json};
+use crate::session::{self, prompt_secret};
+use crate::{EntryFields, Field, GlobalArgs};
+use rand::seq::SliceRandom;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use twopass_core::keyslot::now_millis;
+use twopass_core::reference::ReferenceField;
+use twopass_core::{
+    totp, write_atomic, CustomField, Error, PasswordEntry, PasswordGroup, SecretReference,
+    SecretString,
+};
+
+// 与桌面应用密码生成器的默认字符集相同
+const PASSWORD_CHARSET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789!@#$%^&*()_+-=[]{}|;:,.<>?";
+
+// 按 id 前缀查找时要求的最短长度，避免一两个字符误匹配
+const MIN_ID_PREFIX: usize = 6;
+
+/// 查找条目：完整 id，其次标题（不区分大小写），最后是唯一的 id 前缀
+pub fn find_entry<'a>(
+    entries: &'a [PasswordEntry],
+    query: &str,
+) -> Result<&'a PasswordEntry, Error> {
+    if let Some(entry) = entries.iter().find(|e| e.id == query) {
+        return Ok(entry);
+    }
+    let by_title: Vec<_> = entries
+        .iter()
+        .filter(|e| e.title.to_lowercase() == query.to_lowercase())
+        .collect();
+    let matches = if by_title.is_empty() && query.len() >= MIN_ID_PREFIX {
+        entries.iter().filter(|e| e.id.starts_with(query)).collect()
+    } else {
+        by_title
+    };
+    match matches.as_slice() {
+        [entry] => Ok(entry),
+        [] => Err(Error::EntryNotFound {
+            id: query.to_string(),
+        }),
+        many => {
+            let ids: Vec<_> = many.iter().map(|e| e.id.as_str()).collect();
+            Err(Error::invalid_input(
+                "entry",
+                &format!("{:?} matches several entries: {}", query, ids.join(", ")),
+            ))
+        }
+    }
+}
+
+/// 按 id 或名称（不区分大小写）查找分组
+pub fn find_group<'a>(
+    groups: &'a [PasswordGroup],
+    query: &str,
+) -> Result<&'a PasswordGroup, Error> {
+    groups
+        .iter()
+        .find(|g| g.id == query)
+        .or_else(|| groups.iter().find(|g| g.name.eq_ignore_ascii_case(query)))
+        .ok_or_else(|| Error::GroupNotFound {
+            id: query.to_string(),
+        })
+}
+
+// 和桌面应用列表相同的顺序：有 sort_order 的在前
+fn sorted(entries: &[PasswordEntry]) -> Vec<&PasswordEntry> {
+    let mut sorted: Vec<_> = entries.iter().collect();
+    sorted.sort_by_key(|e| (e.sort_order.is_none(), e.sort_order));
+    sorted
+}
+
+fn totp_code(entry: &PasswordEntry) -> Result<Option<String>, Error> {
+    match entry.totp_secret.as_deref().filter(|s| !s.is_empty()) {
+        Some(secret) => totp::generate(secret).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub fn unlock(global: &GlobalArgs) -> Result<(), Error> {
+    let vault = session::open(global, true)?;
+    let entries = vault.entries()?.len();
+    if global.json {
+        print_json(&json!({ "unlocked": true, "entries": entries }));
+    } else {
+        println!("Vault unlocked, {} entries", entries);
+    }
+    Ok(())
+}
+
+pub fn list(global: &GlobalArgs, group: Option<&str>) -> Result<(), Error> {
+    let vault = session::open(global, true)?;
+    let groups = vault.groups()?;
+    let group_id = group
+        .map(|g| find_group(groups, g))
+        .transpose()?
+        .map(|g| &g.id);
+    let entries: Vec<_> = sorted(vault.entries()?)
+        .into_iter()
+        .filter(|e| group_id.is_none() || e.group_id.as_ref() == group_id)
+        .collect();
+    output::entry_list(&entries, groups, global.json);
+    Ok(())
+}
+
+pub fn search(global: &GlobalArgs, query: &str) -> Result<(), Error> {
+    let vault = session::open(global, true)?;
+    let query = query.to_lowercase();
+    let contains = |text: &str| text.to_lowercase().contains(&query);
+    let entries: Vec<_> = sorted(vault.entries()?)
+        .into_iter()
+        .filter(|e| {
+            contains(&e.title)
+                || contains(&e.username)
+                || contains(&e.notes)
+                || e.url.iter().flatten().any(|url| contains(url))
+                || e.tags.iter().flatten().any(|tag| contains(tag))
+        })
+        .collect();
+    output::entry_list(&entries, vault.groups()?, global.json);
+    Ok(())
+}
+
+pub fn show(global: &GlobalArgs, query: &str, reveal: bool) -> Result<(), Error> {
+    let vault = session::open(global, true)?;
+    let entry = find_entry(vault.entries()?, query)?;
+    let code = totp_code(entry)?;
+    output::entry_detail(entry, vault.groups()?, code.as_deref(), reveal, global.json);
+    Ok(())
+}
+
+pub fn get(global: &GlobalArgs, query: &str, field: Field) -> Result<(), Error> {
+    let vault = session::open(global, true)?;
+    let entry = find_entry(vault.entries()?, query)?;
+    let value = match field {
+        Field::Id => json!(entry.id),
+        Field::Title => json!(entry.title),
+        Field::Username => json!(entry.username),
+        Field::Password => json!(entry.password),
+        Field::Url => json!(entry.url.as_deref().unwrap_or_default()),
+        Field::Notes => json!(entry.notes),
+        Field::Totp => json!(totp_code(entry)?
+            .ok_or_else(|| Error::invalid_input("totp", "the entry has no TOTP secret"))?),
+        Field::TotpSecret => json!(entry.totp_secret.as_deref().unwrap_or_default()),
+        Field::Tags => json!(entry.tags.as_deref().unwrap_or_default()),
+        Field::Group => json!(output::group_name(entry, vault.groups()?)),
+    };
+
+    if global.json {
+        print_json(&value);
+    } else {
+        match value {
+            Value::String(text) => println!("{}", text),
+            Value::Array(items) => items
+                .iter()
+                .filter_map(Value::as_str)
+                .for_each(|item| println!("{}", item)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn generate_password(length: usize) -> Result<SecretString, Error> {
+    if length == 0 {
+        return Err(Error::invalid_input(
+            "generate",
+            "length must be at least 1",
+        ));
+    }
+    let mut rng = rand::thread_rng();
+    let password: String = (0..length)
+        .map(|_| *PASSWORD_CHARSET.choose(&mut rng).unwrap() as char)
+        .collect();
+    Ok(SecretString::new(password))
+}
+
+// 新的条目密码：--password、--generate 或提示输入两次；edit 时都没有给出则不修改
+fn entry_password(
+    fields: &mut EntryFields,
+    ask_by_default: bool,
+) -> Result<Option<SecretString>, Error> {
+    if let Some(password) = fields.password.take() {
+        return Ok(Some(SecretString::new(password)));
+    }
+    if let Some(length) = fields.generate {
+        return generate_password(length).map(Some);
+    }
+    if !fields.ask_password && !ask_by_default {
+        return Ok(None);
+    }
+    let password = prompt_secret("Entry password: ")?;
+    if !password.is_empty() && *prompt_secret("Repeat entry password: ")? != *password {
+        return Err(Error::invalid_input(
+            "password",
+            "the passwords do not match",
+        ));
+    }
+    Ok(Some(password))
+}
+
+// 空字符串表示清除
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|v| !v.is_empty())
+}
+
+// 把 add / edit 给出的字段写入条目（密码除外）
+fn apply_fields(
+    entry: &mut PasswordEntry,
+    fields: EntryFields,
+    groups: &[PasswordGroup],
+) -> Result<(), Error> {
+    if let Some(username) = fields.username {
+        entry.username = username;
+    }
+    if !fields.urls.is_empty() {
+        entry.url = Some(fields.urls);
+    }
+    if let Some(notes) = fields.notes {
+        entry.notes = notes;
+    }
+    if let Some(group) = fields.group {
+        entry.group_id = non_empty(group)
+            .map(|g| find_group(groups, &g).map(|g| g.id.clone()))
+            .transpose()?;
+    }
+    if let Some(secret) = fields.totp_secret {
+        let secret = non_empty(secret);
+        if let Some(secret) = &secret {
+            totp::generate(secret)?; // 提前校验 base32 格式
+        }
+        entry.totp_secret = secret;
+    }
+    if !fields.tags.is_empty() {
+        entry.tags = Some(fields.tags);
+    }
+    for assignment in fields.custom_fields {
+        let (name, value) = assignment
+            .split_once('=')
+            .filter(|(name, _)| !name.trim().is_empty())
+            .ok_or_else(|| Error::invalid_input("field", "expected NAME=VALUE"))?;
+        let custom_fields = entry.custom_fields.get_or_insert_with(Vec::new);
+        custom_fields.retain(|f| !f.name.eq_ignore_ascii_case(name.trim()));
+        if !value.is_empty() {
+            custom_fields.push(CustomField {
+                name: name.trim().to_string(),
+                value: SecretString::from(value),
+            });
+        }
+    }
+    if entry.custom_fields.as_ref().is_some_and(Vec::is_empty) {
+        entry.custom_fields = None;
+    }
+    Ok(())
+}
+
+fn print_saved(global: &GlobalArgs, action: &str, entry: &PasswordEntry) {
+    if global.json {
+        print_json(&json!({ "id": entry.id, "title": entry.title }));
+    } else {
+        println!("{} entry {} ({})", action, entry.title, entry.id);
+    }
+}
+
+pub fn add(global: &GlobalArgs, title: String, mut fields: EntryFields) -> Result<(), Error> {
+    let mut vault = session::open(global, false)?;
+    let password = entry_password(&mut fields, true)?.unwrap_or_default();
+    let now = now_millis();
+    // 和桌面应用新建的条目一致：url 为空数组，没有 sort_order，历史为空
+    let mut entry = PasswordEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        title,
+        username: String::new(),
+        password,
+        url: Some(Vec::new()),
+        notes: String::new(),
+        totp_secret: None,
+        tags: None,
+        group_id: None,
+        sort_order: None,
+        created_at: now,
+        updated_at: now,
+        history: Some(Vec::new()),
+        custom_fields: None,
+    };
+    apply_fields(&mut entry, fields, vault.groups()?)?;
+    vault.add_entry(entry.clone())?;
+    print_saved(global, "Added", &entry);
+    Ok(())
+}
+
+pub fn edit(
+    global: &GlobalArgs,
+    query: &str,
+    title: Option<String>,
+    mut fields: EntryFields,
+) -> Result<(), Error> {
+    let mut vault = session::open(global, false)?;
+    let previous = find_entry(vault.entries()?, query)?.clone();
+    let mut entry = previous.clone();
+    if let Some(title) = title {
+        entry.title = title;
+    }
+    if let Some(password) = entry_password(&mut fields, false)? {
+        entry.password = password;
+    }
+    apply_fields(&mut entry, fields, vault.groups()?)?;
+    entry.updated_at = now_millis();
+    entry.record_history(&previous);
+    vault.update_entry(entry.clone())?;
+    print_saved(global, "Updated", &entry);
+    Ok(())
+}
+
+// 终端中询问确认；非交互使用时必须显式传 --yes
+fn confirm(prompt: &str) -> Result<bool, Error> {
+    if !io::stdin().is_terminal() {
+        return Err(Error::invalid_input(
+            "confirmation",
+            "not running in a terminal, pass --yes to skip it",
+        ));
+    }
+    eprint!("{} [y/N] ", prompt);
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|e| Error::io("Failed to read answer", e))?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+pub fn remove(global: &GlobalArgs, query: &str, yes: bool) -> Result<(), Error> {
+    let mut vault = session::open(global, false)?;
+    let entry = find_entry(vault.entries()?, query)?.clone();
+    if !yes && !confirm(&format!("Delete {:?}?", entry.title))? {
+        eprintln!("Cancelled");
+        return Ok(());
+    }
+    vault.delete_entry(&entry.id)?;
+    print_saved(global, "Deleted", &entry);
+    Ok(())
+}
+
+pub fn read(global: &GlobalArgs, reference: &str) -> Result<(), Error> {
+    let reference: SecretReference = reference.parse()?;
+    let vault = session::open_for_references(global, [&reference])?;
+    let value = reference.resolve(vault.entries()?, vault.groups()?)?;
+    if global.json {
+        print_json(&json!({ "value": value }));
+    } else {
+        println!("{}", &*value);
+    }
+    Ok(())
+}
+
+pub fn reference(global: &GlobalArgs, query: &str, field: &str) -> Result<(), Error> {
+    let field: ReferenceField = field.parse()?;
+    let vault = session::open(global, true)?;
+    let entry = find_entry(vault.entries()?, query)?;
+    let vault_id = session::vault_id(global)?;
+    let reference = SecretReference::for_entry(entry, vault.groups()?, field, vault_id.as_deref());
+    if global.json {
+        print_json(&json!({ "reference": reference.to_string() }));
+    } else {
+        println!("{}", reference);
+    }
+    Ok(())
+}
+
+pub fn totp(global: &GlobalArgs, query: &str) -> Result<(), Error> {
+    let vault = session::open(global, true)?;
+    let entry = find_entry(vault.entries()?, query)?;
+    let code = totp_code(entry)?
+        .ok_or_else(|| Error::invalid_input("totp", "the entry has no TOTP secret"))?;
+    let now_secs = (now_millis() / 1000) as u64;
+    let expires_in = totp::PERIOD_SECS - now_secs % totp::PERIOD_SECS;
+    if global.json {
+        print_json(&json!({ "code": code, "expires_in": expires_in }));
+    } else {
+        println!("{}", code);
+    }
+    Ok(())
+}
+
+/// 导入 Chrome CSV，或合并另一个 2Pass 导出的保险库（提示输入导出文件的密码）
+pub fn import(global: &GlobalArgs, file: &Path, csv: bool) -> Result<(), Error> {
+    let content =
+        fs::read_to_string(file).map_err(|e| Error::io("Failed to read import file", e))?;
+    let mut vault = session::open(global, false)?;
+    let imported = if csv {
+        vault.import_chrome_csv(&content)?
+    } else {
+        let password = prompt_secret("Password of the exported vault: ")?;
+        vault.import_encrypted(&content, password)?
+    };
+    if global.json {
+        print_json(&json!({ "imported": imported }));
+    } else {
+        println!("Imported {} entries", imported);
+    }
+    Ok(())
+}
+
+/// 导出为加密的保险库文件，和桌面应用导出的文件相同，可以在桌面应用中导入
+pub fn export(global: &GlobalArgs, output: Option<&Path>) -> Result<(), Error> {
+    let mut vault = session::open(global, true)?;
+    let data = vault.export()?;
+    match output {
+        Some(path) => {
+            write_atomic(path, data.as_bytes())?;
+            eprintln!("Exported to {}", path.display());
+        }
+        None => println!("{}", data),
+    }
+    Ok(())
+}
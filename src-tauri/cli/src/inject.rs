@@ -0,0 +1,99 @@
+// 2pass inject：渲染配置文件模板，把 {{ 2pass://<分组>/<条目>/<字段> }} 替换成保险库中的值。
+// 输出文件含明文密钥，在 Unix 上只有当前用户可读写
+use crate::output::print_json;
+use crate::session;
+use crate::GlobalArgs;
+use serde_json::json;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use twopass_core::{template, write_private, Error, SecretReference};
+
+pub fn inject(
+    global: &GlobalArgs,
+    input: Option<&Path>,
+    output: Option<&Path>,
+    strict: bool,
+) -> Result<(), Error> {
+    let template = match input {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| Error::io(&format!("Failed to read {}", path.display()), e))?,
+        None if global.password_stdin => {
+            return Err(Error::invalid_input(
+                "input",
+                "--password-stdin needs the template in a file, pass it with -i",
+            ))
+        }
+        None => {
+            let mut template = String::new();
+            io::stdin()
+                .read_to_string(&mut template)
+                .map_err(|e| Error::io("Failed to read the template", e))?;
+            template
+        }
+    };
+
+    // 模板里没有引用时不需要解锁；格式错误的引用由 render 报告
+    let placeholders = template::placeholders(&template);
+    let rendered = if placeholders.is_empty() {
+        template::render(&template, &[], &[])
+    } else {
+        let references: Vec<SecretReference> = placeholders
+            .iter()
+            .filter_map(|p| p.reference.parse().ok())
+            .collect();
+        let vault = session::open_for_references(global, &references)?;
+        template::render(&template, vault.entries()?, vault.groups()?)
+    };
+
+    for unresolved in &rendered.unresolved {
+        let error = unresolved.error.as_ref().unwrap();
+        if strict {
+            eprintln!("2pass: line {}: {}", unresolved.line, error);
+        } else {
+            eprintln!(
+                "2pass: warning: line {}: {}, left unchanged",
+                unresolved.line, error
+            );
+        }
+    }
+    if strict && !rendered.unresolved.is_empty() {
+        return Err(Error::invalid_input(
+            "template",
+            &format!(
+                "{} of {} references could not be resolved",
+                rendered.unresolved.len(),
+                rendered.references
+            ),
+        ));
+    }
+
+    match output {
+        Some(path) => {
+            write_private(path, rendered.output.as_bytes())?;
+            let resolved = rendered.references - rendered.unresolved.len();
+            if global.json {
+                print_json(&json!({
+                    "output": path,
+                    "references": rendered.references,
+                    "unresolved": rendered.unresolved,
+                }));
+            } else {
+                eprintln!(
+                    "Wrote {} ({} of {} references resolved)",
+                    path.display(),
+                    resolved,
+                    rendered.references
+                );
+            }
+        }
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout
+                .write_all(rendered.output.as_bytes())
+                .and_then(|_| stdout.flush())
+                .map_err(|e| Error::io("Failed to write the output", e))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,349 @@
+// 2pass 命令行工具：通过 twopass-core 直接读写桌面应用的保险库，文件格式、数据目录和保险库列表都与桌面应用相同
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use twopass_core::Error;
+
+#[cfg(unix)]
+mod agent;
+mod commands;
+mod inject;
+mod output;
+mod run;
+mod session;
+
+#[derive(Parser)]
+#[command(
+    name = "2pass",
+    version,
+    about = "Command-line client for 2Pass vaults"
+)]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+pub struct GlobalArgs {
+    /// Vault file, or the name or id of a vault added in the desktop app
+    #[arg(long, global = true, env = "TWOPASS_VAULT")]
+    pub vault: Option<String>,
+
+    /// Data directory shared with the desktop app
+    #[arg(long, global = true, env = "TWOPASS_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Key file bound to the master password
+    #[arg(long, global = true, env = "TWOPASS_KEY_FILE")]
+    pub key_file: Option<PathBuf>,
+
+    /// Read the master password from the first line of stdin instead of prompting
+    #[arg(long, global = true)]
+    pub password_stdin: bool,
+
+    /// Print JSON instead of text
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Socket of the unlock agent [default: agent.sock in the data directory]
+    #[arg(long, global = true, env = "TWOPASS_AGENT_SOCK")]
+    pub agent_socket: Option<PathBuf>,
+
+    /// Unlock the vault directly even if an agent holds it
+    #[arg(long, global = true)]
+    pub no_agent: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check the master password without printing anything from the vault
+    Unlock,
+    /// List entries
+    List {
+        /// Only list entries in this group (name or id)
+        #[arg(long)]
+        group: Option<String>,
+    },
+    /// Search titles, usernames, URLs, notes and tags
+    Search { query: String },
+    /// Show an entry
+    Show {
+        /// Entry id, title or id prefix
+        entry: String,
+        /// Show the password and TOTP secret
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Print one field of an entry
+    Get {
+        /// Entry id, title or id prefix
+        entry: String,
+        #[arg(value_enum)]
+        field: Field,
+    },
+    /// Add an entry
+    Add {
+        #[arg(long)]
+        title: String,
+        #[command(flatten)]
+        fields: EntryFields,
+    },
+    /// Change fields of an entry
+    Edit {
+        /// Entry id, title or id prefix
+        entry: String,
+        #[arg(long)]
+        title: Option<String>,
+        #[command(flatten)]
+        fields: EntryFields,
+    },
+    /// Delete an entry
+    Rm {
+        /// Entry id, title or id prefix
+        entry: String,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Print the value of a 2pass:// reference
+    Read {
+        /// 2pass://<group>/<entry>/<field>[?vault=<vault>]
+        reference: String,
+    },
+    /// Print the 2pass:// reference of an entry field, addressed by the entry id
+    Ref {
+        /// Entry id, title or id prefix
+        entry: String,
+        /// Field name, or fields/<name> for a custom field
+        #[arg(default_value = "password")]
+        field: String,
+    },
+    /// Print the current TOTP code of an entry
+    Totp {
+        /// Entry id, title or id prefix
+        entry: String,
+    },
+    /// Import a Chrome CSV export or a 2Pass export into the vault
+    Import {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = ImportFormat::Auto)]
+        format: ImportFormat,
+    },
+    /// Export the vault in the encrypted 2Pass format
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Render a template, replacing {{ 2pass://<group>/<entry>/<field> }} with values from the vault
+    Inject {
+        /// Template file [default: stdin]
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        /// Write to this file, readable only by you, instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Fail if a reference cannot be resolved instead of leaving it unchanged
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Run a command with 2pass:// references in its environment replaced by vault values
+    Run {
+        /// Set NAME to a value or a 2pass://<group>/<entry>/<field> reference, repeat for several
+        #[arg(short, long = "env", value_name = "NAME=VALUE")]
+        env: Vec<String>,
+        /// Read NAME=VALUE lines from a .env file, repeat for several
+        #[arg(long = "env-file", value_name = "FILE")]
+        env_files: Vec<PathBuf>,
+        /// Pass the output of the command through without concealing secrets
+        #[arg(long)]
+        no_masking: bool,
+        /// Command to run and its arguments
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<OsString>,
+    },
+    /// Keep the vault unlocked in a background agent, like ssh-agent
+    #[cfg(unix)]
+    Agent {
+        #[command(subcommand)]
+        command: AgentCommand,
+    },
+}
+
+#[cfg(unix)]
+#[derive(Subcommand)]
+enum AgentCommand {
+    /// Unlock the vault and start the agent in the background
+    Start {
+        /// Lock after this many seconds without requests, 0 to stay unlocked until `2pass agent lock`
+        #[arg(long, value_name = "SECONDS", default_value_t = 600)]
+        ttl: u64,
+        /// Open the vault read-only so the desktop app can still change it
+        #[arg(long)]
+        read_only: bool,
+        /// Run in the foreground instead of detaching (for service managers)
+        #[arg(long)]
+        foreground: bool,
+    },
+    /// Show the agent and the vault it holds
+    Status,
+    /// Lock the vault and stop the agent
+    Lock,
+    /// Agent process started by `agent start`
+    #[command(hide = true)]
+    Serve {
+        #[arg(long)]
+        ttl: u64,
+        #[arg(long)]
+        read_only: bool,
+    },
+}
+
+/// add / edit 共用的字段，edit 时只修改给出的字段
+#[derive(Args)]
+pub struct EntryFields {
+    #[arg(long)]
+    pub username: Option<String>,
+
+    /// Entry password. Visible to other users in the process list, prefer --ask-password
+    #[arg(long, conflicts_with_all = ["generate", "ask_password"])]
+    pub password: Option<String>,
+
+    /// Generate a random password of this length
+    #[arg(long, value_name = "LENGTH", num_args = 0..=1, default_missing_value = "20")]
+    pub generate: Option<usize>,
+
+    /// Prompt for the entry password (default for add)
+    #[arg(long, conflicts_with = "generate")]
+    pub ask_password: bool,
+
+    /// Website, repeat for several (replaces all URLs on edit)
+    #[arg(long = "url")]
+    pub urls: Vec<String>,
+
+    #[arg(long)]
+    pub notes: Option<String>,
+
+    /// Group name or id, an empty string removes the entry from its group
+    #[arg(long)]
+    pub group: Option<String>,
+
+    /// TOTP secret in base32, an empty string removes it
+    #[arg(long)]
+    pub totp_secret: Option<String>,
+
+    /// Tag, repeat for several (replaces all tags on edit)
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+
+    /// Custom field, repeat for several; an empty value removes the field
+    #[arg(long = "field", value_name = "NAME=VALUE")]
+    pub custom_fields: Vec<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Field {
+    Id,
+    Title,
+    Username,
+    Password,
+    Url,
+    Notes,
+    /// Current TOTP code
+    Totp,
+    #[value(name = "totp_secret", alias = "totp-secret")]
+    TotpSecret,
+    Tags,
+    Group,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImportFormat {
+    /// Chrome CSV for .csv files, otherwise 2Pass
+    Auto,
+    Chrome,
+    #[value(name = "2pass")]
+    TwoPass,
+}
+
+fn run(cli: Cli) -> Result<ExitCode, Error> {
+    let global = &cli.global;
+    match cli.command {
+        Command::Unlock => commands::unlock(global),
+        Command::List { group } => commands::list(global, group.as_deref()),
+        Command::Search { query } => commands::search(global, &query),
+        Command::Show { entry, reveal } => commands::show(global, &entry, reveal),
+        Command::Get { entry, field } => commands::get(global, &entry, field),
+        Command::Add { title, fields } => commands::add(global, title, fields),
+        Command::Edit {
+            entry,
+            title,
+            fields,
+        } => commands::edit(global, &entry, title, fields),
+        Command::Rm { entry, yes } => commands::remove(global, &entry, yes),
+        Command::Read { reference } => commands::read(global, &reference),
+        Command::Ref { entry, field } => commands::reference(global, &entry, &field),
+        Command::Totp { entry } => commands::totp(global, &entry),
+        Command::Import { file, format } => {
+            let csv = match format {
+                ImportFormat::Auto => file
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("csv")),
+                ImportFormat::Chrome => true,
+                ImportFormat::TwoPass => false,
+            };
+            commands::import(global, &file, csv)
+        }
+        Command::Export { output } => commands::export(global, output.as_deref()),
+        Command::Inject {
+            input,
+            output,
+            strict,
+        } => inject::inject(global, input.as_deref(), output.as_deref(), strict),
+        // 退出码来自子进程
+        Command::Run {
+            env,
+            env_files,
+            no_masking,
+            command,
+        } => {
+            let options = run::RunOptions {
+                env,
+                env_files,
+                no_masking,
+                command,
+            };
+            return run::run(global, options);
+        }
+        #[cfg(unix)]
+        Command::Agent { command } => match command {
+            AgentCommand::Start {
+                ttl,
+                read_only,
+                foreground,
+            } => agent::start(global, ttl, read_only, foreground),
+            AgentCommand::Status => agent::status(global),
+            AgentCommand::Lock => agent::lock(global),
+            AgentCommand::Serve { ttl, read_only } => {
+                agent::serve_background(global, ttl, read_only)
+            }
+        },
+    }?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let json = cli.global.json;
+    match run(cli) {
+        Ok(code) => code,
+        Err(e) => {
+            output::error(&e, json);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// 命令输出：默认是给人看的文本，--json 时输出 JSON。错误写到 stderr，JSON 格式与桌面应用命令返回的错误相同
+use serde::Serialize;
+use serde_json::{json, Value};
+use twopass_core::{format_timestamp, Error, PasswordEntry, PasswordGroup};
+
+const MASK: &str = "********";
+
+pub fn error(e: &Error, json: bool) {
+    if json {
+        eprintln!("{}", serde_json::to_string(e).unwrap());
+    } else {
+        eprintln!("2pass: {}", e);
+    }
+}
+
+pub fn print_json(value: &impl Serialize) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+pub fn group_name<'a>(entry: &PasswordEntry, groups: &'a [PasswordGroup]) -> Option<&'a str> {
+    let group_id = entry.group_id.as_deref()?;
+    groups
+        .iter()
+        .find(|g| g.id == group_id)
+        .map(|g| g.name.as_str())
+}
+
+/// 列表中的一条：不含密码、TOTP 密钥和备注
+#[derive(Serialize)]
+struct EntrySummary<'a> {
+    id: &'a str,
+    title: &'a str,
+    username: &'a str,
+    url: &'a [String],
+    group: Option<&'a str>,
+    tags: &'a [String],
+    has_totp: bool,
+    updated_at: i64,
+}
+
+fn summary<'a>(entry: &'a PasswordEntry, groups: &'a [PasswordGroup]) -> EntrySummary<'a> {
+    EntrySummary {
+        id: &entry.id,
+        title: &entry.title,
+        username: &entry.username,
+        url: entry.url.as_deref().unwrap_or_default(),
+        group: group_name(entry, groups),
+        tags: entry.tags.as_deref().unwrap_or_default(),
+        has_totp: entry.totp_secret.as_deref().is_some_and(|s| !s.is_empty()),
+        updated_at: entry.updated_at,
+    }
+}
+
+pub fn entry_list(entries: &[&PasswordEntry], groups: &[PasswordGroup], json: bool) {
+    if json {
+        let list: Vec<_> = entries.iter().map(|e| summary(e, groups)).collect();
+        return print_json(&list);
+    }
+    let width = entries
+        .iter()
+        .map(|e| e.title.chars().count())
+        .max()
+        .unwrap_or(0);
+    for entry in entries {
+        let group = group_name(entry, groups)
+            .map(|name| format!("  [{}]", name))
+            .unwrap_or_default();
+        println!(
+            "{}  {:<width$}  {}{}",
+            short_id(&entry.id),
+            entry.title,
+            entry.username,
+            group,
+        );
+    }
+}
+
+// 列表中显示 id 的前 8 位，足够用作 show / get 等命令的 id 前缀
+fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+/// 单个条目。未指定 reveal 时隐藏密码和 TOTP 密钥，JSON 中也不输出修改历史（其中含旧密码）
+pub fn entry_detail(
+    entry: &PasswordEntry,
+    groups: &[PasswordGroup],
+    totp_code: Option<&str>,
+    reveal: bool,
+    json: bool,
+) {
+    if json {
+        let mut value = serde_json::to_value(entry).unwrap();
+        value["group"] = json!(group_name(entry, groups));
+        value["totp"] = json!(totp_code);
+        if !reveal {
+            for key in ["password", "totp_secret"] {
+                if !value[key].is_null() {
+                    value[key] = Value::from(MASK);
+                }
+            }
+            for field in value["custom_fields"].as_array_mut().into_iter().flatten() {
+                field["value"] = Value::from(MASK);
+            }
+            value.as_object_mut().unwrap().remove("history");
+        }
+        return print_json(&value);
+    }
+
+    let line = |label: &str, value: &str| println!("{:<10}{}", format!("{}:", label), value);
+    line("Title", &entry.title);
+    line("Username", &entry.username);
+    if reveal {
+        line("Password", &entry.password);
+    } else if !entry.password.is_empty() {
+        line("Password", &format!("{} (use --reveal to show)", MASK));
+    }
+    for url in entry.url.iter().flatten() {
+        line("URL", url);
+    }
+    if let Some(group) = group_name(entry, groups) {
+        line("Group", group);
+    }
+    if let Some(tags) = entry.tags.as_ref().filter(|tags| !tags.is_empty()) {
+        line("Tags", &tags.join(", "));
+    }
+    if let Some(code) = totp_code {
+        line("TOTP", code);
+    }
+    if reveal {
+        if let Some(secret) = entry.totp_secret.as_deref().filter(|s| !s.is_empty()) {
+            line("Secret", secret);
+        }
+    }
+    for field in entry.custom_fields.iter().flatten() {
+        line(&field.name, if reveal { &field.value } else { MASK });
+    }
+    line("ID", &entry.id);
+    line("Created", &format_timestamp(entry.created_at));
+    line("Updated", &format_timestamp(entry.updated_at));
+    if !entry.notes.is_empty() {
+        println!("Notes:");
+        for note in entry.notes.lines() {
+            println!("  {}", note);
+        }
+    }
+}
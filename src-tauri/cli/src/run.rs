@@ -0,0 +1,193 @@
+// 2pass run：把环境变量中的 2pass:// 引用替换成保险库里的值后启动子进程。
+// 子进程的 stdout / stderr 经过这里转发，其中出现的这些值会被替换成 MASK，避免密钥被打印到终端或 CI 日志里
+use crate::session;
+use crate::GlobalArgs;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, ExitStatus, Stdio};
+use twopass_core::{Error, SecretReference, SecretString};
+
+const MASK: &[u8] = b"<concealed by 2pass>";
+
+pub struct RunOptions {
+    pub env: Vec<String>,
+    pub env_files: Vec<PathBuf>,
+    pub no_masking: bool,
+    pub command: Vec<OsString>,
+}
+
+// NAME=VALUE，NAME 不能为空
+fn split_assignment(text: &str) -> Option<(String, String)> {
+    let (name, value) = text.split_once('=')?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| (name.to_string(), value.to_string()))
+}
+
+// .env 文件：支持空行、# 注释、export 前缀和成对的引号，不处理转义和变量展开
+fn read_env_file(path: &Path, vars: &mut BTreeMap<String, String>) -> Result<(), Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::io(&format!("Failed to read {}", path.display()), e))?;
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = split_assignment(line).ok_or_else(|| {
+            Error::invalid_input(
+                "env-file",
+                &format!(
+                    "{} line {}: expected NAME=VALUE",
+                    path.display(),
+                    number + 1
+                ),
+            )
+        })?;
+        let value = value.trim();
+        let unquoted = ['"', '\'']
+            .iter()
+            .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+            .unwrap_or(value);
+        vars.insert(name, unquoted.to_string());
+    }
+    Ok(())
+}
+
+// 要设置的变量：继承的环境中值为引用的变量，其次 --env-file，最后 --env，后面的覆盖前面的
+fn collect_vars(options: &RunOptions) -> Result<BTreeMap<String, String>, Error> {
+    let mut vars: BTreeMap<_, _> = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(_, value)| SecretReference::is_reference(value))
+        .collect();
+    for path in &options.env_files {
+        read_env_file(path, &mut vars)?;
+    }
+    for assignment in &options.env {
+        let (name, value) = split_assignment(assignment)
+            .ok_or_else(|| Error::invalid_input("env", "expected NAME=VALUE"))?;
+        vars.insert(name, value);
+    }
+    Ok(vars)
+}
+
+pub fn run(global: &GlobalArgs, options: RunOptions) -> Result<ExitCode, Error> {
+    let vars = collect_vars(&options)?;
+    let references: Vec<_> = vars
+        .iter()
+        .filter(|(_, value)| SecretReference::is_reference(value))
+        .map(|(name, value)| Ok((name, value.parse::<SecretReference>()?)))
+        .collect::<Result<_, Error>>()?;
+
+    // 先解析所有引用再关闭保险库，子进程运行期间不保持解锁
+    let mut secrets = BTreeMap::new();
+    if !references.is_empty() {
+        let vault = session::open_for_references(global, references.iter().map(|(_, r)| r))?;
+        for (name, reference) in references {
+            let value = reference.resolve(vault.entries()?, vault.groups()?)?;
+            secrets.insert(name, value);
+        }
+    }
+
+    let (program, args) = options
+        .command
+        .split_first()
+        .ok_or_else(|| Error::invalid_input("command", "no command given"))?;
+    let mut command = Command::new(program);
+    command.args(args);
+    for (name, value) in &vars {
+        match secrets.get(name) {
+            Some(secret) => command.env(name, &**secret),
+            None => command.env(name, value),
+        };
+    }
+
+    // 按长度从长到短匹配，一个值包含另一个值时整体替换
+    let mut masked: Vec<&SecretString> = secrets.values().filter(|s| !s.is_empty()).collect();
+    masked.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    let mask = !options.no_masking && !masked.is_empty();
+    if mask {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| Error::io(&format!("Failed to start {}", program.to_string_lossy()), e))?;
+    // Ctrl-C 会发给整个前台进程组，由子进程决定如何退出；这里忽略它，继续转发子进程剩余的输出
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_IGN);
+        libc::signal(libc::SIGQUIT, libc::SIG_IGN);
+    }
+
+    let status = if mask {
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        std::thread::scope(|scope| {
+            let masked = &masked;
+            scope.spawn(move || forward(stdout, io::stdout(), masked));
+            scope.spawn(move || forward(stderr, io::stderr(), masked));
+            child.wait()
+        })
+    } else {
+        child.wait()
+    }
+    .map_err(|e| Error::io("Failed to wait for the command", e))?;
+    Ok(exit_code(status))
+}
+
+// 子进程的退出码；被信号终止时和 shell 一样返回 128 + 信号
+fn exit_code(status: ExitStatus) -> ExitCode {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return ExitCode::from((128 + signal) as u8);
+        }
+    }
+    ExitCode::from(status.code().unwrap_or(1) as u8)
+}
+
+// 转发子进程的一路输出。每次读到数据都立即写出，只保留末尾可能是某个值开头的几个字节，
+// 等后续数据到达后再判断，这样跨两次读取的值也能被替换
+fn forward(mut input: impl Read, mut output: impl Write, secrets: &[&SecretString]) {
+    let mut pending = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = match input.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => 0,
+        };
+        let done = read == 0;
+        pending.extend_from_slice(&buf[..read]);
+        let (masked, consumed) = mask(&pending, secrets, done);
+        pending.drain(..consumed);
+        // 输出端关闭（例如管道到 head）时继续读取，避免子进程因为写满管道而卡住
+        let _ = output.write_all(&masked).and_then(|_| output.flush());
+        if done {
+            return;
+        }
+    }
+}
+
+// 返回替换后的输出和已处理的字节数；未处理的部分是某个值的前缀，finished 时全部输出
+fn mask(data: &[u8], secrets: &[&SecretString], finished: bool) -> (Vec<u8>, usize) {
+    let mut masked = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        let rest = &data[i..];
+        if let Some(secret) = secrets.iter().find(|s| rest.starts_with(s.as_bytes())) {
+            masked.extend_from_slice(MASK);
+            i += secret.len();
+        } else if !finished && secrets.iter().any(|s| s.as_bytes().starts_with(rest)) {
+            break;
+        } else {
+            masked.push(data[i]);
+            i += 1;
+        }
+    }
+    (masked, i)
+}
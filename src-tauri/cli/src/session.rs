@@ -0,0 +1,268 @@
+// 打开保险库：和桌面应用使用同一个数据目录、保险库列表（vaults.json）、设置和解锁失败记录。
+// 有解锁代理持有同一个保险库时通过代理访问，不再提示输入主密码
+#[cfg(unix)]
+use crate::agent::{self, AgentClient, Request, VaultData};
+use crate::GlobalArgs;
+use directories::BaseDirs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use twopass_core::datadir::{default_vault_path, DataDir, VaultList, APP_IDENTIFIER};
+use twopass_core::keyslot::read_key_file;
+use twopass_core::settings::AppSettings;
+use twopass_core::{
+    attempts, open_storage, Error, PasswordEntry, PasswordGroup, SecretReference, SecretString,
+    UnlockSecret, Vault,
+};
+
+/// 数据目录：和桌面应用的规则相同（twopass_core::datadir），默认目录是桌面应用的应用数据目录
+pub fn data_dir(global: &GlobalArgs) -> Result<PathBuf, Error> {
+    let argument = global.data_dir.clone().map(Into::into);
+    let data_dir = DataDir::resolve(argument, || {
+        let dirs = BaseDirs::new()
+            .ok_or_else(|| Error::io("Failed to get app data dir", "no home directory"))?;
+        Ok(dirs.data_dir().join(APP_IDENTIFIER))
+    })?;
+    Ok(data_dir.path)
+}
+
+/// 保险库文件：--vault 可以是保险库列表中的名称或 id，也可以是文件路径；
+/// 未指定时使用桌面应用当前选中的保险库
+pub fn vault_path(global: &GlobalArgs, data_dir: &Path) -> PathBuf {
+    find_vault(global.vault.as_deref(), data_dir)
+}
+
+/// 当前保险库在保险库列表中的 id；--vault 是列表之外的文件时为 None
+pub fn vault_id(global: &GlobalArgs) -> Result<Option<String>, Error> {
+    let data_dir = data_dir(global)?;
+    let path = vault_path(global, &data_dir);
+    Ok(VaultList::load(&data_dir)
+        .vaults
+        .into_iter()
+        .find(|v| same_path(&v.path, &path))
+        .map(|v| v.id))
+}
+
+fn find_vault(vault: Option<&str>, data_dir: &Path) -> PathBuf {
+    let list = VaultList::load(data_dir);
+    match vault {
+        Some(vault) => list
+            .find(vault)
+            .map_or_else(|| PathBuf::from(vault), |v| v.path.clone()),
+        None => list
+            .active()
+            .map_or_else(|| default_vault_path(data_dir), |v| v.path.clone()),
+    }
+}
+
+/// 读取主密码：--password-stdin 时读取标准输入的第一行，否则在终端提示输入
+pub fn read_master_password(global: &GlobalArgs) -> Result<SecretString, Error> {
+    if global.password_stdin {
+        // 预留足够的容量，读取时不会重新分配；去掉换行后原地交给 SecretString，不留下未清零的副本
+        let mut line = String::with_capacity(1024);
+        io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(|e| Error::io("Failed to read password", e))?;
+        let len = line.trim_end_matches(['\r', '\n']).len();
+        line.truncate(len);
+        return Ok(SecretString::new(line));
+    }
+    prompt_secret("Master password: ")
+}
+
+pub fn prompt_secret(prompt: &str) -> Result<SecretString, Error> {
+    rpassword::prompt_password(prompt)
+        .map(SecretString::new)
+        .map_err(|e| Error::io("Failed to read password", e))
+}
+
+/// 打开保险库。读取类命令只读打开，不占用保险库，桌面应用打开时也可以使用；
+/// 修改类命令需要独占保险库，桌面应用解锁着同一个保险库时返回 VaultInUse
+pub fn open(global: &GlobalArgs, read_only: bool) -> Result<Session, Error> {
+    let data_dir = data_dir(global)?;
+    let path = vault_path(global, &data_dir);
+    open_path(global, &data_dir, &path, read_only)
+}
+
+/// 只读打开引用所在的保险库：引用的 ?vault= 都必须指向同一个保险库，并且和 --vault 一致；
+/// 都没有写 vault 时和 open 相同
+pub fn open_for_references<'a>(
+    global: &GlobalArgs,
+    references: impl IntoIterator<Item = &'a SecretReference>,
+) -> Result<Session, Error> {
+    let data_dir = data_dir(global)?;
+    let default = vault_path(global, &data_dir);
+    let mut paths: Vec<_> = references
+        .into_iter()
+        .filter_map(|r| r.vault.as_deref())
+        .map(|vault| find_vault(Some(vault), &data_dir))
+        .collect();
+    paths.sort();
+    paths.dedup();
+    let path = match paths.as_slice() {
+        [] => default,
+        [path] if global.vault.is_none() || same_path(path, &default) => path.clone(),
+        [path] => {
+            return Err(Error::invalid_input(
+                "vault",
+                &format!(
+                    "the references point to {} but --vault is {}",
+                    path.display(),
+                    default.display()
+                ),
+            ))
+        }
+        _ => {
+            let names: Vec<_> = paths.iter().map(|p| p.display().to_string()).collect();
+            return Err(Error::invalid_input(
+                "vault",
+                &format!(
+                    "the references point to several vaults ({}), use one vault per run",
+                    names.join(", ")
+                ),
+            ));
+        }
+    };
+    open_path(global, &data_dir, &path, true)
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    a == b
+        || a.canonicalize()
+            .ok()
+            .is_some_and(|a| Some(a) == b.canonicalize().ok())
+}
+
+fn open_path(
+    global: &GlobalArgs,
+    data_dir: &Path,
+    path: &Path,
+    read_only: bool,
+) -> Result<Session, Error> {
+    #[cfg(unix)]
+    if let Some(session) = agent::session_for(global, data_dir, path, read_only) {
+        return Ok(session);
+    }
+    unlock(global, data_dir, path, read_only).map(Session::Local)
+}
+
+/// 在当前进程中解锁保险库
+pub fn unlock(
+    global: &GlobalArgs,
+    data_dir: &Path,
+    path: &Path,
+    read_only: bool,
+) -> Result<Vault, Error> {
+    let settings = AppSettings::load(&AppSettings::file(data_dir));
+
+    let mut vault = Vault::new(open_storage(path, settings.backup_retention));
+    if !vault.exists() {
+        return Err(Error::VaultNotFound {
+            id: path.display().to_string(),
+        });
+    }
+
+    let key_file = global.key_file.as_ref().map(read_key_file).transpose()?;
+    let secret = UnlockSecret::Password {
+        password: read_master_password(global)?,
+        key_file,
+    };
+    let unlocked = attempts::throttled(
+        &mut vault,
+        path,
+        settings.wipe_after_failures,
+        read_only,
+        |vault| vault.unlock(secret, read_only),
+    )?;
+    if !unlocked {
+        return Err(Error::WrongPassword);
+    }
+    Ok(vault)
+}
+
+/// 解锁后的保险库：当前进程直接解锁的，或者由代理持有的
+pub enum Session {
+    Local(Vault),
+    #[cfg(unix)]
+    Agent {
+        client: AgentClient,
+        data: VaultData, // 连接时取得的条目和分组
+    },
+}
+
+impl Session {
+    pub fn entries(&self) -> Result<&[PasswordEntry], Error> {
+        match self {
+            Session::Local(vault) => vault.entries(),
+            #[cfg(unix)]
+            Session::Agent { data, .. } => Ok(&data.entries),
+        }
+    }
+
+    pub fn groups(&self) -> Result<&[PasswordGroup], Error> {
+        match self {
+            Session::Local(vault) => vault.groups(),
+            #[cfg(unix)]
+            Session::Agent { data, .. } => Ok(&data.groups),
+        }
+    }
+
+    pub fn add_entry(&mut self, entry: PasswordEntry) -> Result<(), Error> {
+        match self {
+            Session::Local(vault) => vault.add_entry(entry),
+            #[cfg(unix)]
+            Session::Agent { client, .. } => client.call(&Request::AddEntry { entry }),
+        }
+    }
+
+    pub fn update_entry(&mut self, entry: PasswordEntry) -> Result<(), Error> {
+        match self {
+            Session::Local(vault) => vault.update_entry(entry),
+            #[cfg(unix)]
+            Session::Agent { client, .. } => client.call(&Request::UpdateEntry { entry }),
+        }
+    }
+
+    pub fn delete_entry(&mut self, id: &str) -> Result<(), Error> {
+        match self {
+            Session::Local(vault) => vault.delete_entry(id),
+            #[cfg(unix)]
+            Session::Agent { client, .. } => {
+                client.call(&Request::DeleteEntry { id: id.to_string() })
+            }
+        }
+    }
+
+    pub fn import_chrome_csv(&mut self, content: &str) -> Result<usize, Error> {
+        match self {
+            Session::Local(vault) => vault.import_chrome_csv(content),
+            #[cfg(unix)]
+            Session::Agent { client, .. } => client.call(&Request::ImportChromeCsv {
+                content: content.to_string(),
+            }),
+        }
+    }
+
+    pub fn import_encrypted(
+        &mut self,
+        content: &str,
+        password: SecretString,
+    ) -> Result<usize, Error> {
+        match self {
+            Session::Local(vault) => vault.import_encrypted(content, password),
+            #[cfg(unix)]
+            Session::Agent { client, .. } => client.call(&Request::ImportEncrypted {
+                content: content.to_string(),
+                password,
+            }),
+        }
+    }
+
+    pub fn export(&mut self) -> Result<String, Error> {
+        match self {
+            Session::Local(vault) => vault.export(),
+            #[cfg(unix)]
+            Session::Agent { client, .. } => client.call(&Request::Export),
+        }
+    }
+}
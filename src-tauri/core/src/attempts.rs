@@ -0,0 +1,123 @@
+// 解锁失败记录：保存在保险库文件旁边（<文件名>.attempts），每个保险库单独计数，重启后依然有效。
+// 桌面应用和命令行共用同一份记录。连续失败超过一定次数后按指数退避拒绝解锁，可选在失败 N 次后清除保险库
+use crate::keyslot::now_millis;
+use crate::{sibling_file, write_atomic, Error, Vault};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// 前几次失败不限制，之后每次失败等待时间翻倍
+const FREE_ATTEMPTS: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoginAttempts {
+    pub failed_attempts: u32,
+    pub first_failure_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+}
+
+impl LoginAttempts {
+    fn path(data_file: &Path) -> PathBuf {
+        sibling_file(data_file, "attempts")
+    }
+
+    pub fn load(data_file: &Path) -> Self {
+        fs::read_to_string(Self::path(data_file))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, data_file: &Path) -> Result<(), Error> {
+        write_atomic(
+            &Self::path(data_file),
+            serde_json::to_string(self).unwrap().as_bytes(),
+        )
+    }
+
+    fn clear(data_file: &Path) {
+        let path = Self::path(data_file);
+        if path.exists() {
+            if let Err(e) = fs::remove_file(&path) {
+                eprintln!("❌ Failed to reset login attempts: {}", e);
+            }
+        }
+    }
+
+    fn backoff(&self) -> Duration {
+        let Some(exponent) = self.failed_attempts.checked_sub(FREE_ATTEMPTS) else {
+            return Duration::ZERO;
+        };
+        BASE_BACKOFF
+            .checked_mul(2u32.saturating_pow(exponent))
+            .map_or(MAX_BACKOFF, |backoff| backoff.min(MAX_BACKOFF))
+    }
+
+    /// 还需等待多久才能再次尝试
+    pub fn retry_after(&self) -> Duration {
+        let Some(last_failure_at) = self.last_failure_at else {
+            return Duration::ZERO;
+        };
+        let backoff = self.backoff();
+        let elapsed = now_millis().saturating_sub(last_failure_at);
+        // 系统时间被调回时最多等待一个完整的退避周期
+        let elapsed = Duration::from_millis(elapsed.max(0) as u64);
+        backoff.saturating_sub(elapsed)
+    }
+
+    fn record_failure(&mut self) {
+        let now = now_millis();
+        self.failed_attempts += 1;
+        self.first_failure_at.get_or_insert(now);
+        self.last_failure_at = Some(now);
+    }
+}
+
+/// 在解锁外层做限制：退避期内直接拒绝，解锁成功后清空记录，失败时累加，
+/// wipe_after_failures 大于 0 时失败达到次数后清除保险库（只读打开时不清除）
+pub fn throttled(
+    vault: &mut Vault,
+    data_file: &Path,
+    wipe_after_failures: u32,
+    read_only: bool,
+    attempt: impl FnOnce(&mut Vault) -> Result<bool, Error>,
+) -> Result<bool, Error> {
+    let mut attempts = LoginAttempts::load(data_file);
+    let retry_after = attempts.retry_after();
+    if !retry_after.is_zero() {
+        return Err(Error::TooManyAttempts {
+            retry_after_secs: retry_after.as_secs().max(1),
+        });
+    }
+
+    let result = attempt(vault);
+    let failed = match &result {
+        Ok(unlocked) => !unlocked,
+        Err(e) => *e == Error::KeyFileMismatch,
+    };
+    if !failed {
+        if result.is_ok() {
+            LoginAttempts::clear(data_file);
+        }
+        return result;
+    }
+
+    attempts.record_failure();
+    if wipe_after_failures > 0 && attempts.failed_attempts >= wipe_after_failures && !read_only {
+        // 删除保险库文件和所有备份（设置文件保留）
+        vault.wipe()?;
+        LoginAttempts::clear(data_file);
+        eprintln!("🧨 Vault wiped after too many failed unlock attempts");
+        return Err(Error::VaultWiped {
+            failed_attempts: attempts.failed_attempts,
+        });
+    }
+    attempts.save(data_file)?;
+    eprintln!("⚠️ Failed unlock attempt #{}", attempts.failed_attempts);
+
+    result
+}
@@ -0,0 +1,199 @@
+// 密钥派生（Argon2）和数据加解密（AES-256-GCM）
+use crate::{Error, SecretKey, SecretString};
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use zeroize::Zeroizing;
+
+// 旧版本使用的固定盐值，仅用于解锁并迁移旧的保险库
+const LEGACY_SALT: &[u8] = b"2pass_fixed_salt_change_in_prod";
+
+// 新建保险库时使用的 Argon2id 参数
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 4;
+const KDF_SALT_LEN: usize = 16;
+
+// 保险库 header 和导入文件中允许的参数上限，超出时拒绝解锁，避免构造的文件让解锁占用数 GB 内存或运行数小时
+const KDF_MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const KDF_MAX_ITERATIONS: u32 = 16;
+const KDF_MAX_PARALLELISM: u32 = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct KdfParams {
+    algorithm: String,       // argon2id / argon2i / argon2d
+    pub(crate) salt: String, // base64 编码的随机盐值
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl KdfParams {
+    /// 为新保险库生成随机盐值和默认参数
+    pub(crate) fn generate() -> Self {
+        let mut salt = [0u8; KDF_SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self {
+            algorithm: Algorithm::Argon2id.as_str().to_string(),
+            salt: general_purpose::STANDARD.encode(salt),
+            memory_kib: KDF_MEMORY_KIB,
+            iterations: KDF_ITERATIONS,
+            parallelism: KDF_PARALLELISM,
+        }
+    }
+
+    fn check_limits(&self) -> Result<(), Error> {
+        let limits = [
+            ("memory_kib", self.memory_kib, KDF_MAX_MEMORY_KIB),
+            ("iterations", self.iterations, KDF_MAX_ITERATIONS),
+            ("parallelism", self.parallelism, KDF_MAX_PARALLELISM),
+        ];
+        for (name, value, max) in limits {
+            if value == 0 || value > max {
+                return Err(Error::InvalidKdfParams {
+                    reason: format!("{} must be between 1 and {}, got {}", name, max, value),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 没有 header 的旧保险库：固定盐值 + `Argon2::default()` 的参数
+    pub(crate) fn legacy() -> Self {
+        Self {
+            algorithm: Algorithm::Argon2id.as_str().to_string(),
+            salt: general_purpose::STANDARD.encode(LEGACY_SALT),
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+// 用 KEK 加密后的 vault key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct WrappedKey {
+    ciphertext: String,
+    nonce: String,
+}
+
+impl WrappedKey {
+    pub(crate) fn wrap(vault_key: &SecretKey, kek: &SecretKey) -> Result<Self, Error> {
+        let (ciphertext, nonce) = encrypt_data(vault_key, kek)?;
+        Ok(Self { ciphertext, nonce })
+    }
+
+    // KEK 错误时返回 Ok(None)
+    pub(crate) fn unwrap(&self, kek: &SecretKey) -> Result<Option<SecretKey>, Error> {
+        let Some(bytes) = decrypt_bytes(&self.ciphertext, &self.nonce, kek)? else {
+            return Ok(None);
+        };
+        SecretKey::from_slice(&bytes)
+            .map(Some)
+            .ok_or_else(|| Error::crypto("invalid vault key length"))
+    }
+}
+
+pub(crate) fn derive_key(secret: &[u8], kdf: &KdfParams) -> Result<SecretKey, Error> {
+    let invalid = |e: argon2::Error| Error::InvalidKdfParams {
+        reason: e.to_string(),
+    };
+    let algorithm: Algorithm = kdf.algorithm.parse().map_err(invalid)?;
+    kdf.check_limits()?;
+    let params =
+        Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32)).map_err(invalid)?;
+    let salt = general_purpose::STANDARD
+        .decode(&kdf.salt)
+        .map_err(Error::corrupt)?;
+
+    let argon2 = Argon2::new(algorithm, Version::V0x13, params);
+    let mut key = SecretKey::zeroed();
+    argon2
+        .hash_password_into(secret, &salt, key.as_mut_bytes())
+        .map_err(Error::crypto)?;
+    Ok(key)
+}
+
+// 先计算长度再一次性分配缓冲区，避免扩容时在堆上留下未清零的明文副本
+pub(crate) fn to_secret_json<T: Serialize>(value: &T) -> SecretString {
+    struct ByteCounter(usize);
+
+    impl Write for ByteCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, value).unwrap();
+    let mut buffer = Vec::with_capacity(counter.0);
+    serde_json::to_writer(&mut buffer, value).unwrap();
+    SecretString::new(String::from_utf8(buffer).unwrap())
+}
+
+pub(crate) fn encrypt_data(data: &[u8], key: &[u8]) -> Result<(String, String), Error> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(Error::crypto)?;
+    let nonce_bytes = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce_bytes, data).map_err(Error::crypto)?;
+
+    Ok((
+        general_purpose::STANDARD.encode(ciphertext),
+        general_purpose::STANDARD.encode(nonce_bytes),
+    ))
+}
+
+// 认证失败（密钥错误或数据被篡改）时返回 Ok(None)
+pub(crate) fn decrypt_data(
+    encrypted: &str,
+    nonce_str: &str,
+    key: &[u8],
+) -> Result<Option<SecretString>, Error> {
+    let Some(mut plaintext) = decrypt_bytes(encrypted, nonce_str, key)? else {
+        return Ok(None);
+    };
+
+    match String::from_utf8(std::mem::take(&mut *plaintext)) {
+        Ok(plaintext) => Ok(Some(SecretString::new(plaintext))),
+        Err(e) => {
+            drop(Zeroizing::new(e.into_bytes()));
+            Err(Error::corrupt("decrypted data is not valid UTF-8"))
+        }
+    }
+}
+
+pub(crate) fn decrypt_bytes(
+    encrypted: &str,
+    nonce_str: &str,
+    key: &[u8],
+) -> Result<Option<Zeroizing<Vec<u8>>>, Error> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(Error::crypto)?;
+    let ciphertext = general_purpose::STANDARD
+        .decode(encrypted)
+        .map_err(Error::corrupt)?;
+    let nonce_bytes = general_purpose::STANDARD
+        .decode(nonce_str)
+        .map_err(Error::corrupt)?;
+
+    if nonce_bytes.len() != 12 {
+        return Err(Error::corrupt("invalid nonce size"));
+    }
+
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    Ok(cipher
+        .decrypt(nonce, ciphertext.as_ref())
+        .ok()
+        .map(Zeroizing::new))
+}
@@ -0,0 +1,186 @@
+// 数据目录：存放默认保险库、保险库列表（vaults.json）和设置（settings.json），桌面应用和命令行共用。
+// 按以下顺序确定：
+// 1. 命令行参数 --data-dir <路径>
+// 2. 环境变量 TWOPASS_DATA_DIR
+// 3. 便携模式：可执行文件旁边有 2pass.portable 标记文件时，使用可执行文件旁边的 data 目录
+// 4. 系统的应用数据目录
+// 都无法使用时直接报错，不再退回到重启后可能被清空的临时目录
+use crate::{write_atomic, Error};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DATA_DIR_ARG: &str = "--data-dir";
+pub const DATA_DIR_ENV: &str = "TWOPASS_DATA_DIR";
+// 与 tauri.conf.json 中的 identifier 一致，默认数据目录就是系统数据目录下的这个子目录
+pub const APP_IDENTIFIER: &str = "com.twopass.password-manager";
+const PORTABLE_MARKER: &str = "2pass.portable";
+const PORTABLE_DATA_DIR: &str = "data";
+
+const VAULTS_FILE: &str = "vaults.json";
+const DEFAULT_VAULT_FILE: &str = "data.json";
+pub const DEFAULT_VAULT_NAME: &str = "Default";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataDirSource {
+    Argument,
+    Environment,
+    Portable,
+    AppData,
+}
+
+/// 当前使用的数据目录及其来源
+#[derive(Debug, Clone, Serialize)]
+pub struct DataDir {
+    pub path: PathBuf,
+    pub source: DataDirSource,
+}
+
+impl DataDir {
+    /// argument 是命令行给出的 --data-dir；app_data 返回系统的应用数据目录，前三项都没有时才调用
+    pub fn resolve(
+        argument: Option<OsString>,
+        app_data: impl FnOnce() -> Result<PathBuf, Error>,
+    ) -> Result<Self, Error> {
+        let env = std::env::var_os(DATA_DIR_ENV).filter(|value| !value.is_empty());
+        let (value, source) = match (argument, env) {
+            (Some(value), _) => (value, DataDirSource::Argument),
+            (None, Some(value)) => (value, DataDirSource::Environment),
+            (None, None) => {
+                return match Self::portable()? {
+                    Some(data_dir) => Ok(data_dir),
+                    None => Ok(Self {
+                        path: app_data()?,
+                        source: DataDirSource::AppData,
+                    }),
+                }
+            }
+        };
+        // 相对路径按当前工作目录解析
+        let path = std::path::absolute(Path::new(&value))
+            .map_err(|e| Error::invalid_input(DATA_DIR_ARG, &e.to_string()))?;
+        Ok(Self { path, source })
+    }
+
+    /// 从程序参数中找出 --data-dir <路径> 或 --data-dir=<路径>，后面的优先
+    pub fn argument(args: impl IntoIterator<Item = OsString>) -> Result<Option<OsString>, Error> {
+        let mut args = args.into_iter().skip(1);
+        let mut found = None;
+        while let Some(arg) = args.next() {
+            if arg == DATA_DIR_ARG {
+                let value = args
+                    .next()
+                    .ok_or_else(|| Error::invalid_input(DATA_DIR_ARG, "a path is required"))?;
+                found = Some(value);
+            } else if let Some(value) = arg
+                .to_str()
+                .and_then(|a| a.strip_prefix(DATA_DIR_ARG)?.strip_prefix('='))
+            {
+                found = Some(value.into());
+            }
+        }
+        Ok(found)
+    }
+
+    fn portable() -> Result<Option<Self>, Error> {
+        let exe =
+            std::env::current_exe().map_err(|e| Error::io("Failed to locate the executable", e))?;
+        let Some(exe_dir) = exe.parent() else {
+            return Ok(None);
+        };
+        if !exe_dir.join(PORTABLE_MARKER).is_file() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            path: exe_dir.join(PORTABLE_DATA_DIR),
+            source: DataDirSource::Portable,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultEntry {
+    pub id: String,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// 保险库列表（vaults.json）：桌面应用添加的保险库和当前选中的保险库
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultList {
+    pub active: String,
+    pub vaults: Vec<VaultEntry>,
+}
+
+impl VaultList {
+    /// 读取保险库列表，返回的路径都是绝对路径。文件不存在或无法解析时为空列表
+    pub fn load(data_dir: &Path) -> Self {
+        let mut list: Self = fs::read_to_string(data_dir.join(VAULTS_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        // 数据目录内的保险库保存为相对路径，便携模式下整个目录移动后仍然可用
+        for vault in &mut list.vaults {
+            vault.path = data_dir.join(&vault.path);
+        }
+        list
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<(), Error> {
+        let list = Self {
+            active: self.active.clone(),
+            vaults: self
+                .vaults
+                .iter()
+                .map(|v| VaultEntry {
+                    path: v
+                        .path
+                        .strip_prefix(data_dir)
+                        .map_or_else(|_| v.path.clone(), Path::to_path_buf),
+                    ..v.clone()
+                })
+                .collect(),
+        };
+        let json = serde_json::to_string_pretty(&list)
+            .map_err(|e| Error::io("Failed to serialize vault list", e))?;
+        write_atomic(&data_dir.join(VAULTS_FILE), json.as_bytes())
+    }
+
+    /// 列表为空时登记数据目录中的 data.json 为默认保险库（兼容只有一个保险库的旧版本），
+    /// 并保证 active 指向列表中的保险库
+    pub fn ensure_default(&mut self, data_dir: &Path) {
+        if self.vaults.is_empty() {
+            self.vaults.push(VaultEntry {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: DEFAULT_VAULT_NAME.to_string(),
+                path: default_vault_path(data_dir),
+            });
+        }
+        if !self.vaults.iter().any(|v| v.id == self.active) {
+            self.active = self.vaults[0].id.clone();
+        }
+    }
+
+    /// 按 id 或名称（不区分大小写）查找
+    pub fn find(&self, query: &str) -> Option<&VaultEntry> {
+        self.vaults
+            .iter()
+            .find(|v| v.id == query || v.name.eq_ignore_ascii_case(query))
+    }
+
+    /// 当前选中的保险库，没有选中时为列表中的第一个
+    pub fn active(&self) -> Option<&VaultEntry> {
+        self.vaults
+            .iter()
+            .find(|v| v.id == self.active)
+            .or_else(|| self.vaults.first())
+    }
+}
+
+/// 还没有保险库列表时使用的保险库文件
+pub fn default_vault_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(DEFAULT_VAULT_FILE)
+}
@@ -0,0 +1,255 @@
+// 命令返回的错误：序列化为 { code, details }，code 是稳定的错误码，details 是结构化的附加信息，
+// 前端按 code 显示本地化的提示。Display 输出英文描述，用于日志
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "code", content = "details")]
+pub enum Error {
+    // 解锁和认证
+    NotAuthenticated,
+    WrongPassword,
+    KeyFileRequired,
+    KeyFileMismatch,
+    KeyFileNotBound,
+    InvalidKeyFile {
+        reason: String,
+    },
+    InvalidRecoveryKey,
+    PasswordRequired,
+    TooManyAttempts {
+        retry_after_secs: u64,
+    },
+    VaultWiped {
+        failed_attempts: u32,
+    },
+    ResetNotAllowed,
+
+    // 保险库文件
+    ReadOnly,
+    VaultInUse,
+    VaultChangedExternally,
+    VaultReplaced,
+    VaultNotUpgraded,
+    CorruptVault {
+        reason: String,
+    },
+    UnsupportedFormat {
+        version: u32,
+    },
+    InvalidKdfParams {
+        reason: String,
+    },
+
+    // 密码条目和分组
+    EntryNotFound {
+        id: String,
+    },
+    EntryExists {
+        id: String,
+    },
+    GroupNotFound {
+        id: String,
+    },
+    GroupExists {
+        id: String,
+    },
+    GroupNotEmpty {
+        id: String,
+    },
+
+    // key slot 和恢复分片
+    KeySlotNotFound {
+        id: String,
+    },
+    LastPasswordSlot,
+    InvalidShare {
+        reason: String,
+    },
+    ShareChecksumMismatch,
+    NotEnoughShares {
+        required: u8,
+        provided: usize,
+    },
+
+    // 保险库列表
+    VaultNotFound {
+        id: String,
+    },
+    ActiveVault,
+    FileExists {
+        path: String,
+    },
+    NotAVault {
+        path: String,
+        reason: String,
+    },
+
+    // 导入
+    ImportFormat {
+        reason: String,
+    },
+    ImportWrongPassword,
+
+    // 本机 API
+    InvalidApiToken,
+    PermissionDenied {
+        reason: String,
+    },
+    ApiRequestNotFound {
+        id: String,
+    },
+
+    // 密钥引用
+    InvalidReference {
+        reference: String,
+        reason: String,
+    },
+    AmbiguousReference {
+        reference: String,
+        kind: String,
+        ids: Vec<String>,
+    },
+
+    // 其它
+    InvalidTotpSecret {
+        reason: String,
+    },
+    InvalidInput {
+        field: String,
+        reason: String,
+    },
+    Io {
+        reason: String,
+    },
+    Crypto {
+        reason: String,
+    },
+}
+
+impl Error {
+    // 文件读写失败，context 说明在做什么
+    pub fn io(context: &str, e: impl fmt::Display) -> Self {
+        Self::Io {
+            reason: format!("{}: {}", context, e),
+        }
+    }
+
+    pub(crate) fn corrupt(e: impl fmt::Display) -> Self {
+        Self::CorruptVault {
+            reason: e.to_string(),
+        }
+    }
+
+    pub(crate) fn crypto(e: impl fmt::Display) -> Self {
+        Self::Crypto {
+            reason: e.to_string(),
+        }
+    }
+
+    pub fn invalid_input(field: &str, reason: &str) -> Self {
+        Self::InvalidInput {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAuthenticated => write!(f, "Not authenticated"),
+            Self::WrongPassword => write!(f, "Wrong password"),
+            Self::KeyFileRequired => write!(f, "Key file required"),
+            Self::KeyFileMismatch => write!(f, "Key file does not match"),
+            Self::KeyFileNotBound => write!(f, "No key file is bound to this password"),
+            Self::InvalidKeyFile { reason } => write!(f, "Invalid key file: {}", reason),
+            Self::InvalidRecoveryKey => write!(f, "Invalid recovery key"),
+            Self::PasswordRequired => {
+                write!(f, "Legacy vaults can only be unlocked with the master password")
+            }
+            Self::TooManyAttempts { retry_after_secs } => write!(
+                f,
+                "Too many failed attempts. Try again in {}s",
+                retry_after_secs
+            ),
+            Self::VaultWiped { failed_attempts } => write!(
+                f,
+                "Vault wiped after {} failed unlock attempts",
+                failed_attempts
+            ),
+            Self::ResetNotAllowed => write!(
+                f,
+                "Unlock with a recovery key, key file or recovery shares to reset the master password"
+            ),
+            Self::ReadOnly => write!(f, "Vault is opened read-only"),
+            Self::VaultInUse => write!(f, "Vault is already open in another 2Pass instance"),
+            Self::VaultChangedExternally => write!(
+                f,
+                "Vault file was changed by another program, reload it before saving"
+            ),
+            Self::VaultReplaced => write!(f, "Vault file was replaced, please unlock it again"),
+            Self::VaultNotUpgraded => write!(
+                f,
+                "Vault must be unlocked once before changing the master password"
+            ),
+            Self::CorruptVault { reason } => write!(f, "Invalid vault data: {}", reason),
+            Self::UnsupportedFormat { version } => {
+                write!(f, "Unsupported vault format version {}", version)
+            }
+            Self::InvalidKdfParams { reason } => {
+                write!(f, "Unsupported key derivation parameters: {}", reason)
+            }
+            Self::EntryNotFound { id } => write!(f, "Entry not found: {}", id),
+            Self::EntryExists { id } => write!(f, "An entry with id {} already exists", id),
+            Self::GroupNotFound { id } => write!(f, "Group not found: {}", id),
+            Self::GroupExists { id } => write!(f, "A group with id {} already exists", id),
+            Self::GroupNotEmpty { id } => write!(f, "Cannot delete group {} with entries", id),
+            Self::KeySlotNotFound { id } => write!(f, "Key slot not found: {}", id),
+            Self::LastPasswordSlot => write!(f, "Cannot remove the last master password slot"),
+            Self::InvalidShare { reason } => write!(f, "Invalid share: {}", reason),
+            Self::ShareChecksumMismatch => {
+                write!(f, "Share checksum mismatch, please check for typos")
+            }
+            Self::NotEnoughShares { required, provided } => write!(
+                f,
+                "At least {} different shares are required, got {}",
+                required, provided
+            ),
+            Self::VaultNotFound { id } => write!(f, "Vault not found: {}", id),
+            Self::ActiveVault => write!(
+                f,
+                "Cannot forget the active vault, switch to another vault first"
+            ),
+            Self::FileExists { path } => write!(f, "A file already exists at {}", path),
+            Self::NotAVault { path, reason } => {
+                write!(f, "{} is not a 2Pass vault: {}", path, reason)
+            }
+            Self::ImportFormat { reason } => write!(f, "Invalid import file: {}", reason),
+            Self::ImportWrongPassword => write!(f, "Wrong password for the import file"),
+            Self::InvalidApiToken => write!(f, "Invalid or revoked API token"),
+            Self::PermissionDenied { reason } => write!(f, "Permission denied: {}", reason),
+            Self::ApiRequestNotFound { id } => write!(f, "API access request not found: {}", id),
+            Self::InvalidReference { reference, reason } => {
+                write!(f, "Invalid reference {}: {}", reference, reason)
+            }
+            Self::AmbiguousReference {
+                reference,
+                kind,
+                ids,
+            } => write!(
+                f,
+                "Ambiguous reference {}: it matches the {} ids {}, use one of them instead",
+                reference,
+                kind,
+                ids.join(", ")
+            ),
+            Self::InvalidTotpSecret { reason } => write!(f, "Invalid TOTP secret: {}", reason),
+            Self::InvalidInput { field, reason } => write!(f, "Invalid {}: {}", field, reason),
+            Self::Io { reason } => write!(f, "{}", reason),
+            Self::Crypto { reason } => write!(f, "Encryption error: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
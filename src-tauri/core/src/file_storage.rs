@@ -0,0 +1,240 @@
+// 文件存储：整个保险库保存为一个 JSON 文件（data.json），写入前备份为 data.json.bak.<timestamp>，
+// 通过临时文件 + rename 原子替换。解锁期间持有 <文件名>.lock，
+// 并记录最近一次读写后文件的修改时间和大小，用来检测其它程序（另一个实例、同步工具）的修改
+use crate::format::parse_storage_data;
+use crate::lockfile::VaultLock;
+use crate::{Error, Storage, StorageData};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct FileStorage {
+    path: PathBuf,
+    backup_retention: usize, // 保留的 data.json.bak.<timestamp> 数量，0 表示不备份
+    lock: Option<VaultLock>, // 解锁期间持有的跨进程锁
+    stamp: Option<FileStamp>, // 最近一次读写后文件的状态
+}
+
+impl FileStorage {
+    pub fn new(path: impl Into<PathBuf>, backup_retention: usize) -> Self {
+        Self {
+            path: path.into(),
+            backup_retention,
+            lock: None,
+            stamp: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Storage for FileStorage {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    fn read(&self) -> Result<StorageData, Error> {
+        parse_storage_data(&self.export()?)
+    }
+
+    // 先取状态再读取，读取期间发生的修改也能被发现
+    fn read_synced(&mut self) -> Result<StorageData, Error> {
+        let stamp = FileStamp::of(&self.path);
+        let data = self.read()?;
+        self.stamp = stamp;
+        Ok(data)
+    }
+
+    // 先备份旧文件，再原子替换 data.json
+    fn write(&mut self, data: &StorageData) -> Result<(), Error> {
+        // 文件在解锁后被其它程序修改过时拒绝覆盖，由调用方重新加载
+        if self.changed_externally() {
+            return Err(Error::VaultChangedExternally);
+        }
+        backup_data_file(&self.path, self.backup_retention)?;
+        write_atomic(&self.path, serde_json::to_string(data).unwrap().as_bytes())?;
+        self.stamp = FileStamp::of(&self.path);
+        Ok(())
+    }
+
+    // 删除保险库文件和所有备份
+    fn wipe(&mut self) -> Result<(), Error> {
+        self.release();
+        prune_backups(&self.path, 0)?;
+        if self.path.exists() {
+            fs::remove_file(&self.path).map_err(|e| Error::io("Failed to wipe vault", e))?;
+        }
+        Ok(())
+    }
+
+    // 原样导出文件内容
+    fn export(&self) -> Result<String, Error> {
+        fs::read_to_string(&self.path).map_err(|e| Error::io("Failed to read vault", e))
+    }
+
+    // 已持有时不重复获取
+    fn acquire_lock(&mut self) -> Result<(), Error> {
+        if self.lock.is_none() {
+            self.lock = Some(VaultLock::acquire(&self.path)?);
+        }
+        Ok(())
+    }
+
+    fn release(&mut self) {
+        self.lock = None;
+        self.stamp = None;
+    }
+
+    fn changed_externally(&self) -> bool {
+        match self.stamp {
+            Some(stamp) => FileStamp::of(&self.path) != Some(stamp),
+            None => false,
+        }
+    }
+
+    // 保留数量变小时立即清理多余的备份
+    fn set_backup_retention(&mut self, retention: usize) -> Result<(), Error> {
+        if retention < self.backup_retention {
+            prune_backups(&self.path, retention)?;
+        }
+        self.backup_retention = retention;
+        Ok(())
+    }
+}
+
+/// 文件的修改时间和大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl FileStamp {
+    // 文件不存在时为 None
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = path.metadata().ok()?;
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+}
+
+/// 保险库文件旁边的附属文件：<文件名>.<后缀>
+pub fn sibling_file(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// 写入同目录下的临时文件并 fsync，然后 rename 覆盖目标文件
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    write_atomic_with(path, contents, false)
+}
+
+/// 和 write_atomic 相同，但在 Unix 上文件只有当前用户可读写（0600），用于写出含明文密钥的文件
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    write_atomic_with(path, contents, true)
+}
+
+fn write_atomic_with(path: &Path, contents: &[u8], private: bool) -> Result<(), Error> {
+    let mut tmp_name = path
+        .file_name()
+        .ok_or_else(|| Error::invalid_input("path", "not a file path"))?
+        .to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = (|| -> std::io::Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        if private {
+            use std::os::unix::fs::OpenOptionsExt;
+            // 先删除可能残留的临时文件，mode 只在新建文件时生效
+            let _ = fs::remove_file(&tmp_path);
+            options.mode(0o600);
+        }
+        #[cfg(not(unix))]
+        let _ = private;
+        let mut file = options.open(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        sync_parent_dir(path)
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(Error::io("Failed to write file", e));
+    }
+    Ok(())
+}
+
+// rename 之后同步目录，确保新的目录项落盘
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) => fs::File::open(parent)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+fn backup_prefix(path: &Path) -> String {
+    format!(
+        "{}.bak.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    )
+}
+
+// 把当前 data.json 复制为 data.json.bak.<timestamp>，并清理超出保留数量的旧备份
+fn backup_data_file(path: &Path, retention: usize) -> Result<(), Error> {
+    if retention > 0 && path.exists() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| Error::io("System time error", e))?
+            .as_millis();
+        let backup_path = path.with_file_name(format!("{}{}", backup_prefix(path), timestamp));
+        fs::copy(path, &backup_path).map_err(|e| Error::io("Failed to back up vault", e))?;
+    }
+    prune_backups(path, retention)
+}
+
+fn prune_backups(path: &Path, retention: usize) -> Result<(), Error> {
+    let Some(dir) = path.parent() else {
+        return Ok(());
+    };
+    let prefix = backup_prefix(path);
+
+    let mut backups: Vec<(u128, PathBuf)> = fs::read_dir(dir)
+        .map_err(|e| Error::io("Failed to list backups", e))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let timestamp = name.strip_prefix(&prefix)?.parse().ok()?;
+            Some((timestamp, entry.path()))
+        })
+        .collect();
+
+    // 最新的在前，删除超出保留数量的部分
+    backups.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+    for (_, backup_path) in backups.into_iter().skip(retention) {
+        if let Err(e) = fs::remove_file(&backup_path) {
+            eprintln!("❌ Failed to remove old backup {:?}: {}", backup_path, e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,203 @@
+// 保险库文件格式：header（key slot 等）+ 加密后的 AppData，以及旧版本格式的迁移
+use crate::crypto::{decrypt_data, derive_key, KdfParams, WrappedKey};
+use crate::keyslot::{self, KeySlot, KeySlotKind, UnlockSecret};
+use crate::{Error, PasswordEntry, PasswordGroup, SecretKey};
+use serde::{Deserialize, Serialize};
+
+// 保险库文件格式版本：
+// 1: 明文只有 entries 数组，固定盐值，没有 header
+// 2: 明文为 AppData（entries + groups），固定盐值，没有 header
+// 3: 带 header，记录 format_version、随机盐值和 KDF 参数
+// 4: 去掉 master_password_hash，密码是否正确由 AES-GCM 解密是否成功来判断
+// 5: 数据由随机生成的 vault key 加密，header 中保存被主密码派生密钥（KEK）包装的 vault key
+// 6: header 中改为 key slot 列表，每个 slot 用各自的秘密（主密码、恢复密钥、密钥文件）包装同一个 vault key
+pub(crate) const CURRENT_FORMAT_VERSION: u32 = 6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct VaultHeader {
+    pub(crate) format_version: u32,
+    // v3-v5 的单一主密码 KDF 参数和包装后的 vault key，只在读取旧文件时存在
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) kdf: Option<KdfParams>,
+    // v5 之前没有 vault key，数据直接用 KEK 加密
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) wrapped_key: Option<WrappedKey>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) key_slots: Vec<KeySlot>,
+}
+
+impl VaultHeader {
+    pub(crate) fn new(key_slots: Vec<KeySlot>) -> Self {
+        Self {
+            format_version: CURRENT_FORMAT_VERSION,
+            kdf: None,
+            wrapped_key: None,
+            key_slots,
+        }
+    }
+}
+
+/// 保险库文件的内容，存储后端读写的单位
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) header: Option<VaultHeader>,
+    // v4 之前的 Argon2 校验哈希，只在读取旧文件时存在，解锁后即被移除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) master_password_hash: Option<String>,
+    pub(crate) encrypted_data: String,
+    pub(crate) nonce: String,
+}
+
+impl StorageData {
+    fn kdf_params(&self) -> KdfParams {
+        self.header
+            .as_ref()
+            .and_then(|h| h.kdf.clone())
+            .unwrap_or_else(KdfParams::legacy)
+    }
+
+    // 取得数据密钥以及解开它的 slot 类型：v6 依次尝试适用的 key slot，v5 解包 vault key，
+    // 更早的版本直接使用密码派生的密钥。
+    // 解不开时返回 Ok(None)；v5 之前的密码是否正确要等 open_vault 才知道
+    pub(crate) fn unlock_data_key(
+        &self,
+        secret: &UnlockSecret,
+    ) -> Result<Option<(SecretKey, KeySlotKind)>, Error> {
+        if let Some(header) = self.header.as_ref().filter(|h| !h.key_slots.is_empty()) {
+            let unlocked = keyslot::unlock_slots(&header.key_slots, secret, None)?;
+            return Ok(unlocked.map(|(index, key)| (key, header.key_slots[index].kind)));
+        }
+
+        // 旧版本只有主密码
+        let Some(password) = secret.password() else {
+            return Ok(None);
+        };
+        let kek = derive_key(password.as_bytes(), &self.kdf_params())?;
+        let key = match self.header.as_ref().and_then(|h| h.wrapped_key.as_ref()) {
+            Some(wrapped_key) => wrapped_key.unwrap(&kek)?,
+            None => Some(kek),
+        };
+        Ok(key.map(|key| (key, KeySlotKind::Password)))
+    }
+
+    pub(crate) fn format_version(&self) -> Option<u32> {
+        self.header.as_ref().map(|h| h.format_version)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct AppData {
+    pub(crate) entries: Vec<PasswordEntry>,
+    pub(crate) groups: Vec<PasswordGroup>,
+}
+
+// 序列化时借用 Vault 中的数据，避免复制出未清零的明文
+#[derive(Serialize)]
+pub(crate) struct AppDataRef<'a> {
+    pub(crate) entries: &'a [PasswordEntry],
+    pub(crate) groups: &'a [PasswordGroup],
+}
+
+// 解析保险库文件，拒绝当前版本无法识别的格式
+pub(crate) fn parse_storage_data(data: &str) -> Result<StorageData, Error> {
+    let storage_data: StorageData = serde_json::from_str(data).map_err(Error::corrupt)?;
+    if let Some(header) = &storage_data.header {
+        // 比当前版本新的格式，需要升级 2Pass
+        if header.format_version > CURRENT_FORMAT_VERSION {
+            return Err(Error::UnsupportedFormat {
+                version: header.format_version,
+            });
+        }
+    }
+    Ok(storage_data)
+}
+
+// 解密保险库，并把旧版本的数据逐步升级到当前格式
+// 密钥错误（AES-GCM 认证失败）时返回 Ok(None)
+pub(crate) fn open_vault(storage_data: &StorageData, key: &[u8]) -> Result<Option<AppData>, Error> {
+    let Some(decrypted) = decrypt_data(&storage_data.encrypted_data, &storage_data.nonce, key)?
+    else {
+        return Ok(None);
+    };
+
+    // 当前版本直接解析，不经过 serde_json::Value（其中的字符串不会被清零）
+    let format_version = storage_data.format_version();
+    if format_version == Some(CURRENT_FORMAT_VERSION) {
+        return serde_json::from_str(&decrypted)
+            .map(Some)
+            .map_err(Error::corrupt);
+    }
+
+    let document: serde_json::Value = serde_json::from_str(&decrypted).map_err(Error::corrupt)?;
+
+    let version = match format_version {
+        Some(version) => version,
+        // 没有 header 的旧文件：数组为 v1，对象为 v2
+        None if document.is_array() => 1,
+        None => 2,
+    };
+
+    let document = migrate_document(version, document)?;
+    serde_json::from_value(document)
+        .map(Some)
+        .map_err(Error::corrupt)
+}
+
+fn migrate_document(
+    mut version: u32,
+    mut document: serde_json::Value,
+) -> Result<serde_json::Value, Error> {
+    while version < CURRENT_FORMAT_VERSION {
+        document = match version {
+            1 => migrate_v1_to_v2(document),
+            // v3 新增 header，v4 去掉 master_password_hash，v5 新增 vault key，v6 新增 key slot，
+            // 明文结构都不变
+            2..=5 => document,
+            _ => return Err(Error::UnsupportedFormat { version }),
+        };
+        version += 1;
+    }
+    Ok(document)
+}
+
+// v1 -> v2：entries 数组包装为 AppData
+fn migrate_v1_to_v2(document: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "entries": document,
+        "groups": [],
+    })
+}
+
+// 把 v6 之前的单一主密码转换为主密码 key slot，返回之后用于加密数据的 vault key
+pub(crate) fn upgrade_to_key_slot(
+    header: Option<VaultHeader>,
+    key: SecretKey,
+    secret: &UnlockSecret,
+) -> Result<(SecretKey, KeySlot), Error> {
+    let label = keyslot::MASTER_PASSWORD_LABEL;
+    match header {
+        // v5：已经有包装好的 vault key，原样放进 slot
+        Some(VaultHeader {
+            kdf: Some(kdf),
+            wrapped_key: Some(wrapped_key),
+            ..
+        }) => {
+            let slot = KeySlot::from_wrapped(KeySlotKind::Password, label, kdf, wrapped_key);
+            Ok((key, slot))
+        }
+        // v3/v4 数据直接用 KEK 加密：生成随机 vault key，用原来的 KEK 包装
+        Some(VaultHeader { kdf: Some(kdf), .. }) => {
+            let vault_key = SecretKey::random();
+            let slot = KeySlot::with_kek(KeySlotKind::Password, label, kdf, &key, &vault_key)?;
+            Ok((vault_key, slot))
+        }
+        // 固定盐值的旧保险库同时迁移到随机盐值
+        _ => {
+            let password = secret.password().ok_or(Error::PasswordRequired)?;
+            let vault_key = SecretKey::random();
+            let slot = KeySlot::password(label, password, None, &vault_key)?;
+            Ok((vault_key, slot))
+        }
+    }
+}
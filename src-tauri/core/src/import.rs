@@ -0,0 +1,70 @@
+// 导入：Chrome 导出的 CSV，以及 2Pass 导出的加密保险库文件
+use crate::format::{open_vault, parse_storage_data, AppData};
+use crate::keyslot::{now_millis, UnlockSecret};
+use crate::{Error, PasswordEntry, SecretString};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct ChromePasswordEntry {
+    name: String,
+    url: String,
+    username: String,
+    password: SecretString,
+}
+
+// 解析 Chrome 导出的 CSV，sort_order 从 first_sort_order 开始递增
+pub(crate) fn parse_chrome_csv(
+    csv_content: &str,
+    first_sort_order: usize,
+) -> Result<Vec<PasswordEntry>, Error> {
+    let mut reader = csv::Reader::from_reader(csv_content.as_bytes());
+    let now = now_millis();
+
+    let mut entries = Vec::new();
+    for result in reader.deserialize() {
+        let chrome_entry: ChromePasswordEntry = result.map_err(|e| Error::ImportFormat {
+            reason: e.to_string(),
+        })?;
+
+        entries.push(PasswordEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: chrome_entry.name,
+            username: chrome_entry.username,
+            password: chrome_entry.password,
+            url: Some(vec![chrome_entry.url]),
+            notes: String::from("从 Chrome 导入"),
+            totp_secret: None,
+            tags: Some(vec![String::from("Chrome")]),
+            group_id: None,
+            sort_order: Some((first_sort_order + entries.len()) as i64),
+            created_at: now,
+            updated_at: now,
+            history: None,
+            custom_fields: None,
+        });
+    }
+    Ok(entries)
+}
+
+// 用导出时的主密码解开导出的保险库文件
+pub(crate) fn open_encrypted_export(
+    encrypted_json: &str,
+    password: SecretString,
+) -> Result<AppData, Error> {
+    let import_data = parse_storage_data(encrypted_json).map_err(|e| Error::ImportFormat {
+        reason: e.to_string(),
+    })?;
+
+    // 解密数据，解密失败即密码错误
+    let (key, _) = import_data
+        .unlock_data_key(&UnlockSecret::Password {
+            password,
+            key_file: None,
+        })?
+        .ok_or(Error::ImportWrongPassword)?;
+    open_vault(&import_data, &key)
+        .map_err(|e| Error::ImportFormat {
+            reason: e.to_string(),
+        })?
+        .ok_or(Error::ImportWrongPassword)
+}
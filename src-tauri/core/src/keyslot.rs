@@ -0,0 +1,485 @@
+// LUKS 风格的 key slot：每个 slot 用不同的秘密（主密码、恢复密钥、密钥文件）包装同一个 vault key
+use crate::crypto::{derive_key, KdfParams, WrappedKey};
+use crate::{Error, SecretKey, SecretString, Vault};
+use base64::{engine::general_purpose, Engine as _};
+use data_encoding::{BASE32_NOPAD, HEXLOWER};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::digest::generic_array::GenericArray;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+// 恢复密钥：160 位随机数，Base32 编码后每 4 个字符一组
+const RECOVERY_KEY_BYTES: usize = 20;
+const RECOVERY_KEY_GROUP: usize = 4;
+
+// 生成的密钥文件：256 位随机数的十六进制文本
+const KEY_FILE_BYTES: usize = 32;
+
+pub const MASTER_PASSWORD_LABEL: &str = "Master password";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySlotKind {
+    Password,
+    RecoveryKey,
+    KeyFile,
+    Shares, // Shamir 分片合并出的恢复秘密
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct KeySlot {
+    pub(crate) id: String,
+    pub(crate) kind: KeySlotKind,
+    pub(crate) label: String,
+    pub(crate) created_at: i64,
+    pub(crate) kdf: KdfParams,
+    pub(crate) wrapped_key: WrappedKey,
+    // 主密码 slot 绑定了密钥文件时，KDF 输入为 SHA-256(密码) || SHA-256(密钥文件)。
+    // 这里保存密钥文件的校验值，用来区分密钥文件错误和密码错误
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) key_file_check: Option<String>,
+}
+
+impl KeySlot {
+    pub(crate) fn new(
+        kind: KeySlotKind,
+        label: &str,
+        secret: &[u8],
+        vault_key: &SecretKey,
+    ) -> Result<Self, Error> {
+        let kdf = KdfParams::generate();
+        let kek = derive_key(secret, &kdf)?;
+        Self::with_kek(kind, label, kdf, &kek, vault_key)
+    }
+
+    // 主密码 slot，key_file 为 Some 时要求同时提供密钥文件
+    pub(crate) fn password(
+        label: &str,
+        password: &str,
+        key_file: Option<&[u8]>,
+        vault_key: &SecretKey,
+    ) -> Result<Self, Error> {
+        let kdf = KdfParams::generate();
+        let key_file_check = key_file.map(|contents| key_file_check(&kdf, contents));
+        let kek = derive_key(&password_input(password, key_file), &kdf)?;
+        let mut slot = Self::with_kek(KeySlotKind::Password, label, kdf, &kek, vault_key)?;
+        slot.key_file_check = key_file_check;
+        Ok(slot)
+    }
+
+    // 用新的密码/密钥文件重新包装主密码 slot（同时更换盐值），保留 id 和标签
+    pub(crate) fn rewrap_password(
+        &mut self,
+        password: &str,
+        key_file: Option<&[u8]>,
+        vault_key: &SecretKey,
+    ) -> Result<(), Error> {
+        let slot = Self::password(&self.label, password, key_file, vault_key)?;
+        self.kdf = slot.kdf;
+        self.wrapped_key = slot.wrapped_key;
+        self.key_file_check = slot.key_file_check;
+        Ok(())
+    }
+
+    pub(crate) fn with_kek(
+        kind: KeySlotKind,
+        label: &str,
+        kdf: KdfParams,
+        kek: &SecretKey,
+        vault_key: &SecretKey,
+    ) -> Result<Self, Error> {
+        let wrapped_key = WrappedKey::wrap(vault_key, kek)?;
+        Ok(Self::from_wrapped(kind, label, kdf, wrapped_key))
+    }
+
+    pub(crate) fn from_wrapped(
+        kind: KeySlotKind,
+        label: &str,
+        kdf: KdfParams,
+        wrapped_key: WrappedKey,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            label: label.to_string(),
+            created_at: now_millis(),
+            kdf,
+            wrapped_key,
+            key_file_check: None,
+        }
+    }
+
+    // 用这个 slot 的 KDF 参数派生 KEK 并解包 vault key，不适用或解不开时返回 Ok(None)
+    pub(crate) fn unlock(&self, secret: &UnlockSecret) -> Result<Option<SecretKey>, Error> {
+        let Some(slot_secret) = secret.for_slot(self) else {
+            return Ok(None);
+        };
+        let kek = derive_key(&slot_secret, &self.kdf)?;
+        self.wrapped_key.unwrap(&kek)
+    }
+
+    // 绑定了密钥文件的主密码 slot：先检查是否提供了正确的密钥文件，不必运行 KDF
+    fn check_key_file(&self, secret: &UnlockSecret) -> Result<(), Error> {
+        let (UnlockSecret::Password { key_file, .. }, Some(check)) = (secret, &self.key_file_check)
+        else {
+            return Ok(());
+        };
+        match key_file {
+            None => Err(Error::KeyFileRequired),
+            Some(contents) if key_file_check(&self.kdf, contents) != *check => {
+                Err(Error::KeyFileMismatch)
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    pub(crate) fn info(&self) -> KeySlotInfo {
+        KeySlotInfo {
+            id: self.id.clone(),
+            kind: self.kind,
+            label: self.label.clone(),
+            created_at: self.created_at,
+            requires_key_file: self.key_file_check.is_some(),
+        }
+    }
+}
+
+/// slot 的公开信息（不含密钥材料）
+#[derive(Debug, Clone, Serialize)]
+pub struct KeySlotInfo {
+    pub id: String,
+    pub kind: KeySlotKind,
+    pub label: String,
+    pub created_at: i64,
+    pub requires_key_file: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryKeySlot {
+    pub slot: KeySlotInfo,
+    pub recovery_key: SecretString,
+}
+
+/// 用户提供的解锁凭据
+pub enum UnlockSecret {
+    // 主密码（或登录框里输入的恢复密钥），以及可选的第二因素密钥文件
+    Password {
+        password: SecretString,
+        key_file: Option<Zeroizing<Vec<u8>>>,
+    },
+    KeyFile(Zeroizing<Vec<u8>>),
+    Shares(Zeroizing<Vec<u8>>),
+}
+
+impl UnlockSecret {
+    // 转换为某个 slot 的 KDF 输入；输入的密码看起来像恢复密钥时也尝试恢复密钥 slot
+    fn for_slot(&self, slot: &KeySlot) -> Option<Zeroizing<Vec<u8>>> {
+        match (self, slot.kind) {
+            (UnlockSecret::Password { password, key_file }, KeySlotKind::Password) => {
+                let key_file = key_file
+                    .as_deref()
+                    .filter(|_| slot.key_file_check.is_some());
+                Some(password_input(password, key_file.map(Vec::as_slice)))
+            }
+            (UnlockSecret::Password { password, .. }, KeySlotKind::RecoveryKey) => {
+                normalize_recovery_key(password).map(|key| Zeroizing::new(key.as_bytes().to_vec()))
+            }
+            (UnlockSecret::KeyFile(contents), KeySlotKind::KeyFile)
+            | (UnlockSecret::Shares(contents), KeySlotKind::Shares) => {
+                Some(Zeroizing::new(contents.to_vec()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn password(&self) -> Option<&SecretString> {
+        match self {
+            UnlockSecret::Password { password, .. } => Some(password),
+            UnlockSecret::KeyFile(_) | UnlockSecret::Shares(_) => None,
+        }
+    }
+
+    pub fn key_file(&self) -> Option<&[u8]> {
+        match self {
+            UnlockSecret::Password { key_file, .. } => key_file.as_deref().map(Vec::as_slice),
+            UnlockSecret::KeyFile(contents) => Some(contents),
+            UnlockSecret::Shares(_) => None,
+        }
+    }
+}
+
+// 依次尝试 kind 类型（None 表示所有类型）的 slot，返回解开的 slot 下标和 vault key。
+// 都解不开时，如果有 slot 因为密钥文件缺失或不匹配被跳过，返回对应的错误
+pub(crate) fn unlock_slots(
+    slots: &[KeySlot],
+    secret: &UnlockSecret,
+    kind: Option<KeySlotKind>,
+) -> Result<Option<(usize, SecretKey)>, Error> {
+    let mut key_file_error = None;
+    for (index, slot) in slots.iter().enumerate() {
+        if kind.is_some_and(|kind| kind != slot.kind) {
+            continue;
+        }
+        if let Err(e) = slot.check_key_file(secret) {
+            key_file_error = Some(e);
+            continue;
+        }
+        if let Some(key) = slot.unlock(secret)? {
+            return Ok(Some((index, key)));
+        }
+    }
+    key_file_error.map_or(Ok(None), Err)
+}
+
+// 主密码 slot 的 KDF 输入：未绑定密钥文件时就是密码本身
+fn password_input(password: &str, key_file: Option<&[u8]>) -> Zeroizing<Vec<u8>> {
+    let Some(key_file) = key_file else {
+        return Zeroizing::new(password.as_bytes().to_vec());
+    };
+    let mut input = Zeroizing::new(vec![0u8; 64]);
+    let (password_hash, key_file_hash) = input.split_at_mut(32);
+    Sha256::new()
+        .chain_update(password.as_bytes())
+        .finalize_into(GenericArray::from_mut_slice(password_hash));
+    Sha256::new()
+        .chain_update(key_file)
+        .finalize_into(GenericArray::from_mut_slice(key_file_hash));
+    input
+}
+
+// 密钥文件校验值：和 slot 的盐值一起哈希，不同 slot 之间无法关联
+fn key_file_check(kdf: &KdfParams, key_file: &[u8]) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"2pass key file check")
+        .chain_update(kdf.salt.as_bytes())
+        .chain_update(Sha256::digest(key_file))
+        .finalize();
+    general_purpose::STANDARD.encode(&digest[..16])
+}
+
+pub fn read_key_file(path: impl AsRef<Path>) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let contents = Zeroizing::new(
+        fs::read(path.as_ref()).map_err(|e| Error::io("Failed to read key file", e))?,
+    );
+    if contents.is_empty() {
+        return Err(Error::InvalidKeyFile {
+            reason: "file is empty".to_string(),
+        });
+    }
+    Ok(contents)
+}
+
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+// 生成 XXXX-XXXX-... 格式的恢复密钥
+pub(crate) fn generate_recovery_key() -> SecretString {
+    let mut bytes = Zeroizing::new([0u8; RECOVERY_KEY_BYTES]);
+    rand::rngs::OsRng.fill_bytes(&mut bytes[..]);
+    group_base32(&bytes[..])
+}
+
+// Base32 编码后每 4 个字符用 - 分组，便于抄写
+pub(crate) fn group_base32(bytes: &[u8]) -> SecretString {
+    let encoded = Zeroizing::new(BASE32_NOPAD.encode(bytes));
+
+    let mut grouped = String::with_capacity(encoded.len() + encoded.len() / RECOVERY_KEY_GROUP);
+    for (i, c) in encoded.chars().enumerate() {
+        if i > 0 && i % RECOVERY_KEY_GROUP == 0 {
+            grouped.push('-');
+        }
+        grouped.push(c);
+    }
+    SecretString::new(grouped)
+}
+
+// 去掉分隔符和空白并转为大写；不是合法的恢复密钥时返回 None
+pub(crate) fn normalize_recovery_key(input: &str) -> Option<SecretString> {
+    let normalized: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let normalized = SecretString::new(normalized);
+
+    let decoded = Zeroizing::new(BASE32_NOPAD.decode(normalized.as_bytes()).ok()?);
+    (decoded.len() == RECOVERY_KEY_BYTES).then_some(normalized)
+}
+
+// 生成新的随机密钥文件，不覆盖已有文件
+pub fn generate_key_file(path: impl AsRef<Path>) -> Result<(), Error> {
+    let mut bytes = Zeroizing::new([0u8; KEY_FILE_BYTES]);
+    rand::rngs::OsRng.fill_bytes(&mut bytes[..]);
+    let contents = Zeroizing::new(HEXLOWER.encode(&bytes[..]));
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| Error::io("Failed to create key file", e))?;
+    file.write_all(contents.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| Error::io("Failed to write key file", e))
+}
+
+impl Vault {
+    pub fn add_password_slot(
+        &mut self,
+        label: &str,
+        password: &str,
+        key_file: Option<&[u8]>,
+    ) -> Result<KeySlotInfo, Error> {
+        self.update_key_slots(|slots, vault_key| {
+            let slot = KeySlot::password(label, password, key_file, vault_key)?;
+            let info = slot.info();
+            slots.push(slot);
+            Ok(info)
+        })
+    }
+
+    // 生成新的恢复密钥并登记为 key slot，返回的恢复密钥只会出现这一次
+    pub fn add_recovery_key(&mut self, label: &str) -> Result<RecoveryKeySlot, Error> {
+        let recovery_key = generate_recovery_key();
+        let normalized = normalize_recovery_key(&recovery_key).ok_or(Error::InvalidRecoveryKey)?;
+
+        let slot = self.update_key_slots(|slots, vault_key| {
+            let slot = KeySlot::new(
+                KeySlotKind::RecoveryKey,
+                label,
+                normalized.as_bytes(),
+                vault_key,
+            )?;
+            let info = slot.info();
+            slots.push(slot);
+            Ok(info)
+        })?;
+
+        Ok(RecoveryKeySlot { slot, recovery_key })
+    }
+
+    pub fn add_key_file_slot(
+        &mut self,
+        label: &str,
+        key_file: &[u8],
+    ) -> Result<KeySlotInfo, Error> {
+        self.update_key_slots(|slots, vault_key| {
+            let slot = KeySlot::new(KeySlotKind::KeyFile, label, key_file, vault_key)?;
+            let info = slot.info();
+            slots.push(slot);
+            Ok(info)
+        })
+    }
+
+    pub fn remove_key_slot(&mut self, id: &str) -> Result<(), Error> {
+        self.update_key_slots(|slots, _| {
+            let index = slots
+                .iter()
+                .position(|s| s.id == id)
+                .ok_or_else(|| Error::KeySlotNotFound { id: id.to_string() })?;
+
+            // 至少保留一个主密码 slot，保证日常解锁方式不会丢失
+            let is_last_password = slots[index].kind == KeySlotKind::Password
+                && slots
+                    .iter()
+                    .filter(|s| s.kind == KeySlotKind::Password)
+                    .count()
+                    == 1;
+            if is_last_password {
+                return Err(Error::LastPasswordSlot);
+            }
+
+            slots.remove(index);
+            Ok(())
+        })
+    }
+
+    // 用恢复密钥、密钥文件或恢复分片解锁后（忘记主密码的情况），直接设置新的主密码
+    pub fn reset_master_password(&mut self, new_password: &str) -> Result<(), Error> {
+        if !matches!(
+            self.unlocked_with(),
+            Some(KeySlotKind::RecoveryKey | KeySlotKind::KeyFile | KeySlotKind::Shares)
+        ) {
+            return Err(Error::ResetNotAllowed);
+        }
+        self.replace_password_slots(new_password)
+    }
+
+    // 用新的主密码 slot 替换所有主密码 slot（忘记主密码后的恢复流程）
+    pub fn replace_password_slots(&mut self, new_password: &str) -> Result<(), Error> {
+        self.update_key_slots(|slots, vault_key| {
+            let slot = KeySlot::password(MASTER_PASSWORD_LABEL, new_password, None, vault_key)?;
+            slots.retain(|s| s.kind != KeySlotKind::Password);
+            slots.insert(0, slot);
+            Ok(())
+        })
+    }
+
+    // 让主密码 slot 额外要求密钥文件：之后解锁需要同时提供密码和密钥文件
+    pub fn bind_key_file(
+        &mut self,
+        master_password: SecretString,
+        key_file: &[u8],
+    ) -> Result<KeySlotInfo, Error> {
+        let secret = UnlockSecret::Password {
+            password: master_password,
+            key_file: None,
+        };
+
+        self.update_key_slots(|slots, _| {
+            let mut unlocked = None;
+            for (index, slot) in slots.iter().enumerate() {
+                if slot.kind != KeySlotKind::Password || slot.key_file_check.is_some() {
+                    continue;
+                }
+                if let Some(vault_key) = slot.unlock(&secret)? {
+                    unlocked = Some((index, vault_key));
+                    break;
+                }
+            }
+            // 密码错误，或该主密码已绑定密钥文件
+            let (index, vault_key) = unlocked.ok_or(Error::WrongPassword)?;
+
+            let slot = &mut slots[index];
+            slot.rewrap_password(secret.password().unwrap(), Some(key_file), &vault_key)?;
+            Ok(slot.info())
+        })
+    }
+
+    // 解除主密码 slot 与密钥文件的绑定，需要同时提供密码和当前的密钥文件
+    pub fn unbind_key_file(
+        &mut self,
+        master_password: SecretString,
+        key_file: Zeroizing<Vec<u8>>,
+    ) -> Result<KeySlotInfo, Error> {
+        let secret = UnlockSecret::Password {
+            password: master_password,
+            key_file: Some(key_file),
+        };
+
+        self.update_key_slots(|slots, _| {
+            let (index, vault_key) = unlock_slots(slots, &secret, Some(KeySlotKind::Password))?
+                .ok_or(Error::WrongPassword)?;
+            let slot = &mut slots[index];
+            if slot.key_file_check.is_none() {
+                return Err(Error::KeyFileNotBound);
+            }
+
+            slot.rewrap_password(secret.password().unwrap(), None, &vault_key)?;
+            Ok(slot.info())
+        })
+    }
+}
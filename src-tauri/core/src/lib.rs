@@ -0,0 +1,109 @@
+// 2Pass 保险库引擎：加密、文件格式、key slot、存储、导入导出、TOTP，以及数据目录中的保险库列表和设置。
+// 不依赖 Tauri，桌面应用的命令、命令行工具和测试都通过 Vault 使用同一套逻辑
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+pub mod attempts;
+mod crypto;
+pub mod datadir;
+mod error;
+mod file_storage;
+mod format;
+mod import;
+pub mod keyslot;
+mod lockfile;
+mod memory_storage;
+pub mod reference;
+mod secret;
+pub mod settings;
+pub mod shares;
+mod sqlite_storage;
+mod storage;
+pub mod template;
+mod time;
+pub mod totp;
+#[cfg(unix)]
+pub mod unix_socket;
+mod vault;
+
+pub use error::Error;
+pub use file_storage::{sibling_file, write_atomic, write_private, FileStamp, FileStorage};
+pub use format::StorageData;
+pub use keyslot::{KeySlotInfo, KeySlotKind, RecoveryKeySlot, UnlockSecret};
+pub use memory_storage::MemoryStorage;
+pub use reference::SecretReference;
+pub use secret::{SecretKey, SecretString};
+pub use sqlite_storage::SqliteStorage;
+pub use storage::{is_sqlite_path, open_storage, EncryptedRecord, RecordKind, Storage};
+pub use time::format_timestamp;
+pub use vault::Vault;
+
+// 每个条目最多保留的修改历史，和桌面应用编辑条目时一致
+const MAX_HISTORY: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Zeroize)]
+pub struct PasswordHistory {
+    pub timestamp: i64,
+    pub password: Option<SecretString>,
+    pub username: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// 自定义字段，例如 API 密钥、PIN，可以通过 2pass://<分组>/<条目>/fields/<名称> 引用
+#[derive(Debug, Clone, Serialize, Deserialize, Zeroize)]
+pub struct CustomField {
+    pub name: String,
+    pub value: SecretString,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Zeroize)]
+pub struct PasswordEntry {
+    pub id: String,
+    pub title: String,
+    pub username: String,
+    pub password: SecretString,
+    pub url: Option<Vec<String>>,
+    pub notes: String,
+    pub totp_secret: Option<String>, // TOTP secret in base32 format
+    pub tags: Option<Vec<String>>,   // 标签列表
+    pub group_id: Option<String>,    // 所属分组ID
+    pub sort_order: Option<i64>,     // 排序顺序
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub history: Option<Vec<PasswordHistory>>,   // 修改历史
+    pub custom_fields: Option<Vec<CustomField>>, // 自定义字段
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Zeroize)]
+pub struct PasswordGroup {
+    pub id: String,
+    pub name: String,
+    pub icon: String,
+    pub color: Option<String>,
+    pub sort_order: i64,
+    pub created_at: i64,
+}
+
+impl PasswordEntry {
+    /// 编辑后调用：密码、用户名或备注有变化时，把 previous 中变化字段的旧值记入历史
+    pub fn record_history(&mut self, previous: &PasswordEntry) {
+        let mut history = previous.history.clone().unwrap_or_default();
+
+        let password_changed = *self.password != *previous.password;
+        let username_changed = self.username != previous.username;
+        let notes_changed = self.notes != previous.notes;
+        if password_changed || username_changed || notes_changed {
+            history.insert(
+                0,
+                PasswordHistory {
+                    timestamp: previous.updated_at,
+                    password: password_changed.then(|| previous.password.clone()),
+                    username: username_changed.then(|| previous.username.clone()),
+                    notes: notes_changed.then(|| previous.notes.clone()),
+                },
+            );
+            history.truncate(MAX_HISTORY);
+        }
+        self.history = Some(history);
+    }
+}
@@ -0,0 +1,36 @@
+// 跨进程的保险库锁：解锁期间持有保险库文件旁边 <文件名>.lock 的排他锁（建议锁），
+// 同一个保险库只能被一个 2Pass 实例打开。进程退出或崩溃时由操作系统自动释放
+use crate::{sibling_file, Error};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug)]
+pub(crate) struct VaultLock {
+    _file: File, // 文件关闭时锁即释放，锁文件本身保留
+}
+
+impl VaultLock {
+    pub(crate) fn acquire(data_file: &Path) -> Result<Self, Error> {
+        let path = sibling_file(data_file, "lock");
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| Error::io("Failed to open lock file", e))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Err(Error::VaultInUse),
+            Err(TryLockError::Error(e)) => return Err(Error::io("Failed to lock vault", e)),
+        }
+
+        // 记录持有者的进程号，方便排查
+        if file.set_len(0).is_ok() {
+            let _ = write!(file, "{}", std::process::id());
+        }
+        Ok(Self { _file: file })
+    }
+}
@@ -0,0 +1,44 @@
+// 内存存储：保险库只保存在内存中，进程退出即消失，用于测试和临时保险库
+use crate::format::parse_storage_data;
+use crate::{Error, Storage, StorageData};
+
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    data: Option<String>, // 序列化后的 StorageData，和文件后端写入的内容相同
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn location(&self) -> String {
+        String::from("memory")
+    }
+
+    fn exists(&self) -> bool {
+        self.data.is_some()
+    }
+
+    fn read(&self) -> Result<StorageData, Error> {
+        parse_storage_data(&self.export()?)
+    }
+
+    fn write(&mut self, data: &StorageData) -> Result<(), Error> {
+        self.data = Some(serde_json::to_string(data).unwrap());
+        Ok(())
+    }
+
+    fn wipe(&mut self) -> Result<(), Error> {
+        self.data = None;
+        Ok(())
+    }
+
+    fn export(&self) -> Result<String, Error> {
+        self.data
+            .clone()
+            .ok_or_else(|| Error::io("Failed to read vault", "no vault in memory"))
+    }
+}
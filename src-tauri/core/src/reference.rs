@@ -0,0 +1,339 @@
+// 密钥引用：用一个 URI 指向保险库中某个条目的某个字段，用于环境变量、配置文件模板和本机 API。
+//
+//   2pass://<分组>/<条目>/<字段>[?vault=<保险库>]
+//   2pass://<分组>/<条目>/fields/<自定义字段名>[?vault=<保险库>]
+//
+// 条目可以写 id 或标题：id 在整个保险库中查找，不受分组影响，条目改名或移动分组后引用仍然有效；
+// 标题只在给定的分组中查找（不区分大小写），分组写名称或 id，为空（2pass:///标题/字段）表示未分组的条目。
+// vault 是保险库列表中的名称或 id，省略时使用当前的保险库。名称中的 "/"、"%"、"?"、"&" 写成 %2F、%25、%3F、%26
+use crate::{totp, Error, PasswordEntry, PasswordGroup, SecretString};
+use std::fmt;
+use std::str::FromStr;
+
+pub const SCHEME: &str = "2pass://";
+const CUSTOM_FIELDS: &str = "fields";
+
+/// 可以引用的字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceField {
+    Id,
+    Title,
+    Username,
+    Password,
+    Url,
+    Notes,
+    Tags,
+    TotpSecret,
+    /// 当前的 TOTP 验证码
+    Totp,
+    /// 自定义字段，按名称查找（不区分大小写）
+    Custom(String),
+}
+
+impl ReferenceField {
+    const BUILTIN: [(&'static str, Self); 9] = [
+        ("id", Self::Id),
+        ("title", Self::Title),
+        ("username", Self::Username),
+        ("password", Self::Password),
+        ("url", Self::Url),
+        ("notes", Self::Notes),
+        ("tags", Self::Tags),
+        ("totp_secret", Self::TotpSecret),
+        ("totp", Self::Totp),
+    ];
+}
+
+impl FromStr for ReferenceField {
+    type Err = Error;
+
+    /// 内置字段名，或 fields/<名称>
+    fn from_str(name: &str) -> Result<Self, Error> {
+        if let Some(custom) = name
+            .strip_prefix(CUSTOM_FIELDS)
+            .and_then(|n| n.strip_prefix('/'))
+        {
+            if custom.is_empty() {
+                return Err(Error::invalid_input(
+                    "field",
+                    "the custom field name is empty",
+                ));
+            }
+            return Ok(Self::Custom(custom.to_string()));
+        }
+        Self::BUILTIN
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, f)| f.clone())
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::BUILTIN.iter().map(|(n, _)| *n).collect();
+                Error::invalid_input(
+                    "field",
+                    &format!(
+                        "unknown field {:?}, expected one of {} or fields/<name>",
+                        name,
+                        names.join(", ")
+                    ),
+                )
+            })
+    }
+}
+
+impl fmt::Display for ReferenceField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Custom(name) => write!(f, "{}/{}", CUSTOM_FIELDS, encode(name)),
+            builtin => {
+                let (name, _) = Self::BUILTIN.iter().find(|(_, b)| b == builtin).unwrap();
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretReference {
+    /// 保险库列表中的名称或 id，None 表示当前保险库
+    pub vault: Option<String>,
+    /// 分组名称或 id，空字符串表示未分组
+    pub group: String,
+    /// 条目 id 或标题
+    pub entry: String,
+    pub field: ReferenceField,
+}
+
+impl SecretReference {
+    pub fn is_reference(text: &str) -> bool {
+        text.starts_with(SCHEME)
+    }
+
+    /// 条目的规范引用：条目用 id，分组写名称方便阅读（按 id 查找时不使用）
+    pub fn for_entry(
+        entry: &PasswordEntry,
+        groups: &[PasswordGroup],
+        field: ReferenceField,
+        vault: Option<&str>,
+    ) -> Self {
+        let group = entry
+            .group_id
+            .as_deref()
+            .and_then(|id| groups.iter().find(|g| g.id == id))
+            .map(|g| g.name.clone())
+            .unwrap_or_default();
+        Self {
+            vault: vault.map(str::to_string),
+            group,
+            entry: entry.id.clone(),
+            field,
+        }
+    }
+
+    /// 引用是否指向这个保险库；没有写 vault 的引用指向当前保险库
+    pub fn matches_vault(&self, id: &str, name: &str) -> bool {
+        self.vault
+            .as_deref()
+            .is_none_or(|vault| vault == id || vault.eq_ignore_ascii_case(name))
+    }
+
+    fn find_group<'a>(&self, groups: &'a [PasswordGroup]) -> Result<&'a PasswordGroup, Error> {
+        if let Some(group) = groups.iter().find(|g| g.id == self.group) {
+            return Ok(group);
+        }
+        let matches: Vec<_> = groups
+            .iter()
+            .filter(|g| g.name.to_lowercase() == self.group.to_lowercase())
+            .collect();
+        match matches.as_slice() {
+            [group] => Ok(group),
+            [] => Err(Error::GroupNotFound {
+                id: self.group.clone(),
+            }),
+            _ => Err(Error::AmbiguousReference {
+                reference: self.to_string(),
+                kind: "group".to_string(),
+                ids: matches.iter().map(|g| g.id.clone()).collect(),
+            }),
+        }
+    }
+
+    /// 找到引用的条目；标题或分组名有重名时返回 AmbiguousReference，而不是随便取一个
+    pub fn find_entry<'a>(
+        &self,
+        entries: &'a [PasswordEntry],
+        groups: &[PasswordGroup],
+    ) -> Result<&'a PasswordEntry, Error> {
+        if let Some(entry) = entries.iter().find(|e| e.id == self.entry) {
+            return Ok(entry);
+        }
+
+        let group_id = if self.group.is_empty() {
+            None
+        } else {
+            Some(self.find_group(groups)?.id.as_str())
+        };
+        let matches: Vec<_> = entries
+            .iter()
+            .filter(|e| e.group_id.as_deref() == group_id)
+            .filter(|e| e.title.to_lowercase() == self.entry.to_lowercase())
+            .collect();
+        match matches.as_slice() {
+            [entry] => Ok(entry),
+            [] => Err(Error::EntryNotFound {
+                id: self.to_string(),
+            }),
+            _ => Err(Error::AmbiguousReference {
+                reference: self.to_string(),
+                kind: "entry".to_string(),
+                ids: matches.iter().map(|e| e.id.clone()).collect(),
+            }),
+        }
+    }
+
+    /// 解析出字段的值；url 和 tags 有多个时用换行连接
+    pub fn resolve(
+        &self,
+        entries: &[PasswordEntry],
+        groups: &[PasswordGroup],
+    ) -> Result<SecretString, Error> {
+        let entry = self.find_entry(entries, groups)?;
+        let value = match &self.field {
+            ReferenceField::Id => entry.id.clone(),
+            ReferenceField::Title => entry.title.clone(),
+            ReferenceField::Username => entry.username.clone(),
+            ReferenceField::Password => String::from(&*entry.password),
+            ReferenceField::Url => entry.url.as_deref().unwrap_or_default().join("\n"),
+            ReferenceField::Notes => entry.notes.clone(),
+            ReferenceField::Tags => entry.tags.as_deref().unwrap_or_default().join("\n"),
+            ReferenceField::TotpSecret => self.totp_secret(entry)?.to_string(),
+            ReferenceField::Totp => totp::generate(self.totp_secret(entry)?)?,
+            ReferenceField::Custom(name) => entry
+                .custom_fields
+                .iter()
+                .flatten()
+                .find(|f| f.name.eq_ignore_ascii_case(name))
+                .map(|f| String::from(&*f.value))
+                .ok_or_else(|| self.invalid(&format!("the entry has no field {:?}", name)))?,
+        };
+        Ok(SecretString::new(value))
+    }
+
+    fn totp_secret<'a>(&self, entry: &'a PasswordEntry) -> Result<&'a str, Error> {
+        entry
+            .totp_secret
+            .as_deref()
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| self.invalid("the entry has no TOTP secret"))
+    }
+
+    fn invalid(&self, reason: &str) -> Error {
+        Error::InvalidReference {
+            reference: self.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl FromStr for SecretReference {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidReference {
+            reference: text.to_string(),
+            reason: reason.to_string(),
+        };
+        let rest = text
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| invalid("must start with 2pass://"))?;
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+
+        let mut vault = None;
+        for pair in query.into_iter().flat_map(|q| q.split('&')) {
+            match pair.split_once('=') {
+                Some(("vault", value)) if !value.is_empty() => {
+                    vault = Some(
+                        decode(value).ok_or_else(|| invalid("invalid %-escape in the vault"))?,
+                    );
+                }
+                _ => {
+                    return Err(invalid(&format!(
+                        "unknown parameter {:?}, only vault= is supported",
+                        pair
+                    )))
+                }
+            }
+        }
+
+        let parts: Vec<_> = path.split('/').collect();
+        let (group, entry, field) = match parts.as_slice() {
+            [group, entry, field] => (group, entry, field.to_string()),
+            [group, entry, CUSTOM_FIELDS, name] => {
+                let name = decode(name).ok_or_else(|| invalid("invalid %-escape in the field"))?;
+                (group, entry, format!("{}/{}", CUSTOM_FIELDS, name))
+            }
+            _ => return Err(invalid(
+                "expected 2pass://<group>/<entry>/<field> or 2pass://<group>/<entry>/fields/<name>",
+            )),
+        };
+        if entry.is_empty() {
+            return Err(invalid("the entry is empty"));
+        }
+        let field = field.parse().map_err(|e| match e {
+            Error::InvalidInput { reason, .. } => invalid(&reason),
+            e => e,
+        })?;
+        Ok(Self {
+            vault,
+            group: decode(group).ok_or_else(|| invalid("invalid %-escape in the group"))?,
+            entry: decode(entry).ok_or_else(|| invalid("invalid %-escape in the entry"))?,
+            field,
+        })
+    }
+}
+
+impl fmt::Display for SecretReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}/{}/{}",
+            SCHEME,
+            encode(&self.group),
+            encode(&self.entry),
+            self.field
+        )?;
+        if let Some(vault) = &self.vault {
+            write!(f, "?vault={}", encode(vault))?;
+        }
+        Ok(())
+    }
+}
+
+// 只转义会影响拆分的字符，其余保持原样，方便手写
+fn encode(segment: &str) -> String {
+    segment
+        .replace('%', "%25")
+        .replace('/', "%2F")
+        .replace('?', "%3F")
+        .replace('&', "%26")
+}
+
+fn decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = segment
+                .get(i + 1..i + 3)
+                .filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()))?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
@@ -0,0 +1,200 @@
+// 敏感数据的内存封装：drop 时清零，并尽量 mlock 所在的内存页，避免被换出到 swap
+use rand::RngCore;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::Deref;
+use zeroize::Zeroize;
+
+pub const KEY_LEN: usize = 32;
+
+/// 256 位密钥，保存在单独的堆内存中
+pub struct SecretKey(Box<[u8; KEY_LEN]>);
+
+impl SecretKey {
+    pub fn zeroed() -> Self {
+        let key = Box::new([0u8; KEY_LEN]);
+        memlock::lock(key.as_ptr(), KEY_LEN);
+        Self(key)
+    }
+
+    pub fn random() -> Self {
+        let mut key = Self::zeroed();
+        rand::rngs::OsRng.fill_bytes(key.as_mut_bytes());
+        key
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != KEY_LEN {
+            return None;
+        }
+        let mut key = Self::zeroed();
+        key.as_mut_bytes().copy_from_slice(bytes);
+        Some(key)
+    }
+
+    pub fn as_mut_bytes(&mut self) -> &mut [u8] {
+        &mut self.0[..]
+    }
+}
+
+impl Deref for SecretKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+        memlock::unlock(self.0.as_ptr(), KEY_LEN);
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(***)")
+    }
+}
+
+/// 不可变的敏感字符串（主密码、条目密码、解密后的明文等）
+///
+/// 内容只能通过 `Deref<Target = str>` 读取，缓冲区不会重新分配，
+/// 因此 drop 时可以完整地清零并解除锁定。
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: String) -> Self {
+        memlock::lock(value.as_ptr(), value.capacity());
+        Self(value)
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+impl Default for SecretString {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
+}
+
+impl Deref for SecretString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Clone for SecretString {
+    fn clone(&self) -> Self {
+        Self::new(self.0.clone())
+    }
+}
+
+impl Zeroize for SecretString {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        let (ptr, capacity) = (self.0.as_ptr(), self.0.capacity());
+        self.0.zeroize();
+        memlock::unlock(ptr, capacity);
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"***\"")
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+// mlock 以页为单位，多个秘密可能共享同一页，所以按页做引用计数，
+// 最后一个使用者释放时才 munlock。锁定失败（如超出 RLIMIT_MEMLOCK）时静默忽略。
+#[cfg(unix)]
+mod memlock {
+    use std::collections::BTreeMap;
+    use std::sync::{Mutex, OnceLock};
+
+    static LOCKED_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+    fn page_size() -> usize {
+        static PAGE_SIZE: OnceLock<usize> = OnceLock::new();
+        *PAGE_SIZE.get_or_init(|| {
+            let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+            if size > 0 {
+                size as usize
+            } else {
+                4096
+            }
+        })
+    }
+
+    fn pages(ptr: *const u8, len: usize) -> impl Iterator<Item = usize> {
+        let page_size = page_size();
+        let start = ptr as usize & !(page_size - 1);
+        let end = if len == 0 { start } else { ptr as usize + len };
+        (start..end).step_by(page_size)
+    }
+
+    pub fn lock(ptr: *const u8, len: usize) {
+        let mut locked = LOCKED_PAGES.lock().unwrap_or_else(|e| e.into_inner());
+        for page in pages(ptr, len) {
+            let count = locked.entry(page).or_insert(0);
+            if *count == 0 {
+                unsafe {
+                    libc::mlock(page as *const libc::c_void, page_size());
+                }
+            }
+            *count += 1;
+        }
+    }
+
+    pub fn unlock(ptr: *const u8, len: usize) {
+        let mut locked = LOCKED_PAGES.lock().unwrap_or_else(|e| e.into_inner());
+        for page in pages(ptr, len) {
+            if let Some(count) = locked.get_mut(&page) {
+                *count -= 1;
+                if *count == 0 {
+                    locked.remove(&page);
+                    unsafe {
+                        libc::munlock(page as *const libc::c_void, page_size());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod memlock {
+    pub fn lock(_ptr: *const u8, _len: usize) {}
+
+    pub fn unlock(_ptr: *const u8, _len: usize) {}
+}
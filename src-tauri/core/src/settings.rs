@@ -0,0 +1,44 @@
+// 应用设置（数据目录中的 settings.json）：对所有保险库通用，桌面应用和命令行读取同一份
+use crate::{write_atomic, Error};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SETTINGS_FILE: &str = "settings.json";
+pub const DEFAULT_BACKUP_RETENTION: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub backup_retention: usize, // 保留的 data.json.bak.<timestamp> 数量，0 表示不备份
+    pub auto_lock_minutes: u64,  // 空闲多少分钟后自动锁定，0 表示禁用
+    pub wipe_after_failures: u32, // 连续解锁失败多少次后清除保险库，0 表示禁用
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            backup_retention: DEFAULT_BACKUP_RETENTION,
+            auto_lock_minutes: 0,
+            wipe_after_failures: 0,
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn file(data_dir: &Path) -> PathBuf {
+        data_dir.join(SETTINGS_FILE)
+    }
+
+    /// 文件不存在或无法解析时使用默认设置
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        write_atomic(path, serde_json::to_string(self).unwrap().as_bytes())
+    }
+}
@@ -0,0 +1,469 @@
+// Shamir 秘密共享：随机恢复秘密登记为 key slot，再拆成 N 份，任意 K 份即可合并出恢复秘密解锁保险库
+use crate::keyslot::{self, KeySlot, KeySlotInfo, KeySlotKind};
+use crate::{Error, SecretString, Vault};
+use data_encoding::BASE32_NOPAD;
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use zeroize::Zeroizing;
+
+const SECRET_LEN: usize = 32;
+const SHARE_VERSION: u8 = 1;
+const CHECKSUM_LEN: usize = 4;
+// 分片的二进制格式：版本 | 阈值 | 序号 | 分片值 | 校验和
+const SHARE_HEADER_LEN: usize = 3;
+const SHARE_LEN: usize = SHARE_HEADER_LEN + SECRET_LEN + CHECKSUM_LEN;
+
+// 类似 otpauth:// 的 URI，secret 参数即文本格式的分片
+const SHARE_URI_PREFIX: &str = "2pass-share://recovery/";
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryShare {
+    pub index: u8,
+    pub text: SecretString,
+    pub uri: SecretString,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryShares {
+    pub slot: KeySlotInfo,
+    pub threshold: u8,
+    pub shares: Vec<RecoveryShare>,
+}
+
+struct Share {
+    threshold: u8,
+    index: u8,
+    value: Zeroizing<[u8; SECRET_LEN]>,
+}
+
+impl Vault {
+    // 生成随机恢复秘密并登记为 key slot，再拆成 share_count 份，任意 threshold 份即可解锁
+    pub fn create_recovery_shares(
+        &mut self,
+        threshold: u8,
+        share_count: u8,
+        label: &str,
+    ) -> Result<RecoveryShares, Error> {
+        if threshold < 2 || threshold > share_count {
+            return Err(Error::invalid_input(
+                "threshold",
+                "must be at least 2 and no more than the number of shares",
+            ));
+        }
+
+        let label = if label.trim().is_empty() {
+            format!("Recovery shares ({} of {})", threshold, share_count)
+        } else {
+            label.to_string()
+        };
+
+        let mut secret = Zeroizing::new([0u8; SECRET_LEN]);
+        rand::rngs::OsRng.fill_bytes(&mut secret[..]);
+
+        let slot = self.update_key_slots(|slots, vault_key| {
+            let slot = KeySlot::new(KeySlotKind::Shares, &label, &secret[..], vault_key)?;
+            let info = slot.info();
+            slots.push(slot);
+            Ok(info)
+        })?;
+
+        let shares = split(&secret, threshold, share_count)
+            .into_iter()
+            .map(|share| {
+                let text = encode_share(&share);
+                let uri = share_uri(&label, share_count, &share, &text);
+                RecoveryShare {
+                    index: share.index,
+                    text,
+                    uri,
+                }
+            })
+            .collect();
+
+        eprintln!(
+            "🧩 Split recovery secret into {} shares (threshold {})",
+            share_count, threshold
+        );
+
+        Ok(RecoveryShares {
+            slot,
+            threshold,
+            shares,
+        })
+    }
+}
+
+// 解码并合并分片，得到用于 UnlockSecret::Shares 的恢复秘密
+pub fn combine_shares(shares: &[SecretString]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let shares = shares
+        .iter()
+        .map(|share| decode_share(share))
+        .collect::<Result<Vec<_>, _>>()?;
+    combine(&shares)
+}
+
+fn encode_share(share: &Share) -> SecretString {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(SHARE_LEN));
+    bytes.extend_from_slice(&[SHARE_VERSION, share.threshold, share.index]);
+    bytes.extend_from_slice(&share.value[..]);
+    let checksum = Sha256::digest(&bytes[..]);
+    bytes.extend_from_slice(&checksum[..CHECKSUM_LEN]);
+    keyslot::group_base32(&bytes)
+}
+
+fn share_uri(label: &str, share_count: u8, share: &Share, text: &str) -> SecretString {
+    // 容量足够时不会重新分配；分片直接逐字符写入，不经过未清零的中间字符串
+    let mut uri = String::with_capacity(SHARE_URI_PREFIX.len() + label.len() * 3 + 160);
+    let _ = write!(
+        uri,
+        "{}{}?secret=",
+        SHARE_URI_PREFIX,
+        urlencoding::encode(label)
+    );
+    uri.extend(text.chars().filter(|c| *c != '-'));
+    let _ = write!(
+        uri,
+        "&threshold={}&shares={}&index={}",
+        share.threshold, share_count, share.index
+    );
+    SecretString::new(uri)
+}
+
+// 接受文本格式（大小写、分隔符和空白不限）或 URI 格式的分片
+fn decode_share(input: &str) -> Result<Share, Error> {
+    let input = input.trim();
+    let text = match input.strip_prefix(SHARE_URI_PREFIX) {
+        Some(rest) => rest
+            .split_once('?')
+            .and_then(|(_, query)| query.split('&').find_map(|p| p.strip_prefix("secret=")))
+            .ok_or_else(|| invalid_share("share URI has no secret"))?,
+        None => input,
+    };
+
+    let normalized = SecretString::new(
+        text.chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect(),
+    );
+    let bytes = Zeroizing::new(
+        BASE32_NOPAD
+            .decode(normalized.as_bytes())
+            .map_err(|_| invalid_share("invalid encoding"))?,
+    );
+    if bytes.len() != SHARE_LEN {
+        return Err(invalid_share("invalid length"));
+    }
+
+    let (payload, checksum) = bytes.split_at(SHARE_LEN - CHECKSUM_LEN);
+    if Sha256::digest(payload)[..CHECKSUM_LEN] != *checksum {
+        return Err(Error::ShareChecksumMismatch);
+    }
+    let [version, threshold, index] = [payload[0], payload[1], payload[2]];
+    if version != SHARE_VERSION {
+        return Err(invalid_share(&format!("unsupported version {}", version)));
+    }
+    if threshold < 2 || index == 0 {
+        return Err(invalid_share("invalid threshold or index"));
+    }
+
+    let mut value = Zeroizing::new([0u8; SECRET_LEN]);
+    value.copy_from_slice(&payload[SHARE_HEADER_LEN..]);
+    Ok(Share {
+        threshold,
+        index,
+        value,
+    })
+}
+
+fn invalid_share(reason: &str) -> Error {
+    Error::InvalidShare {
+        reason: reason.to_string(),
+    }
+}
+
+// 对每个字节构造随机的 K-1 次多项式 f，f(0) 为秘密，第 x 份分片为 f(x)
+fn split(secret: &[u8; SECRET_LEN], threshold: u8, share_count: u8) -> Vec<Share> {
+    let mut shares: Vec<Share> = (1..=share_count)
+        .map(|index| Share {
+            threshold,
+            index,
+            value: Zeroizing::new([0u8; SECRET_LEN]),
+        })
+        .collect();
+
+    let mut coefficients = Zeroizing::new(vec![0u8; threshold as usize]);
+    for (i, &byte) in secret.iter().enumerate() {
+        coefficients[0] = byte;
+        rand::rngs::OsRng.fill_bytes(&mut coefficients[1..]);
+        for share in shares.iter_mut() {
+            // Horner 法求值
+            share.value[i] = coefficients
+                .iter()
+                .rev()
+                .fold(0, |acc, &c| gf_mul(acc, share.index) ^ c);
+        }
+    }
+    shares
+}
+
+// 拉格朗日插值求 f(0)
+fn combine(shares: &[Share]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let threshold = shares
+        .first()
+        .ok_or(Error::NotEnoughShares {
+            required: 2,
+            provided: 0,
+        })?
+        .threshold;
+    if shares.iter().any(|s| s.threshold != threshold) {
+        return Err(invalid_share("shares come from different splits"));
+    }
+
+    // 同一份分片输入两次只算一份；序号相同而值不同说明混入了别的拆分，不能随便取一个
+    let mut distinct: Vec<&Share> = Vec::new();
+    for share in shares {
+        match distinct.iter().find(|s| s.index == share.index) {
+            None => distinct.push(share),
+            Some(existing) if existing.value[..] == share.value[..] => {}
+            Some(_) => {
+                return Err(invalid_share(&format!(
+                    "two different shares have the index {}",
+                    share.index
+                )))
+            }
+        }
+    }
+    if distinct.len() < threshold as usize {
+        return Err(Error::NotEnoughShares {
+            required: threshold,
+            provided: distinct.len(),
+        });
+    }
+    let used = &distinct[..threshold as usize];
+
+    let mut secret = Zeroizing::new(vec![0u8; SECRET_LEN]);
+    for (j, share_j) in used.iter().enumerate() {
+        let mut basis = 1u8;
+        for (m, share_m) in used.iter().enumerate() {
+            if m != j {
+                let denominator = share_m.index ^ share_j.index;
+                basis = gf_mul(basis, gf_mul(share_m.index, gf_inv(denominator)));
+            }
+        }
+        for (byte, &y) in secret.iter_mut().zip(share_j.value.iter()) {
+            *byte ^= gf_mul(basis, y);
+        }
+    }
+    Ok(secret)
+}
+
+// GF(2^8) 乘法（AES 多项式 x^8 + x^4 + x^3 + x + 1），不按秘密分支
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+// a^254 = a^-1
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_secret() -> Zeroizing<[u8; SECRET_LEN]> {
+        let mut secret = Zeroizing::new([0u8; SECRET_LEN]);
+        rand::rngs::OsRng.fill_bytes(&mut secret[..]);
+        secret
+    }
+
+    fn texts(shares: &[Share]) -> Vec<SecretString> {
+        shares.iter().map(encode_share).collect()
+    }
+
+    // 从 n 份中取出所有 k 份的组合
+    fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+        if k == 0 {
+            return vec![Vec::new()];
+        }
+        (k - 1..n)
+            .flat_map(|last| {
+                combinations(last, k - 1).into_iter().map(move |mut c| {
+                    c.push(last);
+                    c
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gf_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1, "inverse of {}", a);
+        }
+    }
+
+    #[test]
+    fn any_threshold_shares_recover_the_secret() {
+        for (k, n) in [(2, 2), (2, 3), (3, 5), (4, 6), (5, 5)] {
+            let secret = random_secret();
+            let shares = texts(&split(&secret, k, n));
+            for picked in combinations(n as usize, k as usize) {
+                let subset: Vec<_> = picked.iter().map(|&i| shares[i].clone()).collect();
+                let recovered = combine_shares(&subset).unwrap();
+                assert_eq!(recovered[..], secret[..], "k={} n={} {:?}", k, n, picked);
+            }
+            // 多于 k 份时同样可以恢复
+            assert_eq!(combine_shares(&shares).unwrap()[..], secret[..]);
+        }
+    }
+
+    #[test]
+    fn large_split_round_trips() {
+        let secret = random_secret();
+        let shares = texts(&split(&secret, 10, 255));
+        let subset: Vec<_> = shares.iter().rev().step_by(25).cloned().collect();
+        assert_eq!(subset.len(), 11);
+        assert_eq!(combine_shares(&subset).unwrap()[..], secret[..]);
+    }
+
+    #[test]
+    fn text_and_uri_forms_decode() {
+        let secret = random_secret();
+        let shares = split(&secret, 2, 3);
+        let uri = share_uri("My vault", 3, &shares[0], &encode_share(&shares[0]));
+        // 小写、空白和去掉分隔符的文本同样可以识别
+        let loose = SecretString::new(format!(
+            "  {} ",
+            encode_share(&shares[2]).replace('-', " ").to_lowercase()
+        ));
+        let recovered = combine_shares(&[uri, loose]).unwrap();
+        assert_eq!(recovered[..], secret[..]);
+    }
+
+    #[test]
+    fn fewer_than_threshold_shares_fail() {
+        let secret = random_secret();
+        let shares = texts(&split(&secret, 3, 5));
+        assert_eq!(
+            combine_shares(&shares[..2]).unwrap_err(),
+            Error::NotEnoughShares {
+                required: 3,
+                provided: 2,
+            }
+        );
+        assert_eq!(
+            combine_shares(&[]).unwrap_err(),
+            Error::NotEnoughShares {
+                required: 2,
+                provided: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn corrupted_shares_are_rejected() {
+        let secret = random_secret();
+        let share = encode_share(&split(&secret, 2, 3)[0]);
+
+        // 改动一个字符：仍是合法的 base32，但校验和不符
+        let mut typo = share.to_string();
+        let replacement = if typo.as_bytes()[10] == b'A' {
+            "B"
+        } else {
+            "A"
+        };
+        typo.replace_range(10..11, replacement);
+        assert_eq!(
+            decode_share(&typo).err(),
+            Some(Error::ShareChecksumMismatch)
+        );
+
+        // 截断、非 base32 字符、URI 中没有 secret
+        let truncated = &share[..share.len() - 8];
+        assert!(matches!(
+            decode_share(truncated).err(),
+            Some(Error::InvalidShare { .. })
+        ));
+        let garbled = share.replace(&share[..1], "!");
+        assert!(matches!(
+            decode_share(&garbled).err(),
+            Some(Error::InvalidShare { .. })
+        ));
+        let no_secret = format!("{}label?threshold=2", SHARE_URI_PREFIX);
+        assert!(matches!(
+            decode_share(&no_secret).err(),
+            Some(Error::InvalidShare { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_header_is_rejected_even_with_a_valid_checksum() {
+        let secret = random_secret();
+        let mut share = split(&secret, 2, 3).remove(0);
+        share.index = 0;
+        assert!(matches!(
+            decode_share(&encode_share(&share)).err(),
+            Some(Error::InvalidShare { .. })
+        ));
+        share.index = 1;
+        share.threshold = 1;
+        assert!(matches!(
+            decode_share(&encode_share(&share)).err(),
+            Some(Error::InvalidShare { .. })
+        ));
+    }
+
+    #[test]
+    fn duplicate_indexes_are_rejected() {
+        let secret = random_secret();
+        let shares = texts(&split(&secret, 2, 3));
+
+        // 同一份分片输入两次不能凑够阈值
+        assert_eq!(
+            combine_shares(&[shares[0].clone(), shares[0].clone()]).unwrap_err(),
+            Error::NotEnoughShares {
+                required: 2,
+                provided: 1,
+            }
+        );
+
+        // 另一次拆分中序号相同的分片
+        let other = texts(&split(&random_secret(), 2, 3));
+        assert!(matches!(
+            combine_shares(&[shares[0].clone(), other[0].clone(), shares[1].clone()]).unwrap_err(),
+            Error::InvalidShare { .. }
+        ));
+    }
+
+    #[test]
+    fn shares_from_different_thresholds_are_rejected() {
+        let secret = random_secret();
+        let two = texts(&split(&secret, 2, 3));
+        let three = texts(&split(&secret, 3, 3));
+        assert!(matches!(
+            combine_shares(&[two[0].clone(), three[1].clone()]).unwrap_err(),
+            Error::InvalidShare { .. }
+        ));
+    }
+}
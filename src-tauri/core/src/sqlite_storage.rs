@@ -0,0 +1,283 @@
+// SQLite 存储：header（key slot 等）和用于校验密钥的空 AppData 保存在 vault 表，
+// 每个条目和分组单独加密后保存为 records 表中的一行，修改一条记录只需写入这一行。
+// 数据库的 user_version 用作修订号，每次写入在同一个事务中加一，用来发现其它程序的修改
+use crate::format::parse_storage_data;
+use crate::lockfile::VaultLock;
+use crate::{sibling_file, EncryptedRecord, Error, RecordKind, Storage, StorageData};
+use rusqlite::{
+    params, Connection, OpenFlags, OptionalExtension, Transaction, TransactionBehavior,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS vault (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS records (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    encrypted_data TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    PRIMARY KEY (kind, id)
+);
+";
+
+pub struct SqliteStorage {
+    path: PathBuf,
+    lock: Option<VaultLock>, // 解锁期间持有的跨进程锁
+    revision: Option<i32>,   // 最近一次读写后的修订号
+}
+
+impl SqliteStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: None,
+            revision: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // 只读取时不创建数据库文件
+    fn connect(&self, create: bool) -> Result<Connection, Error> {
+        let flags = if create {
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        };
+        let conn = Connection::open_with_flags(&self.path, flags)
+            .map_err(|e| Error::io("Failed to open vault database", e))?;
+        if create {
+            conn.execute_batch(SCHEMA)
+                .map_err(|e| Error::io("Failed to create vault database", e))?;
+        }
+        Ok(conn)
+    }
+
+    // 在一个事务中写入：修订号和 read_synced 时不同说明被其它程序修改过，拒绝覆盖；
+    // 写入成功后修订号加一
+    fn transact(
+        &mut self,
+        write: impl FnOnce(&Transaction) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut conn = self.connect(true)?;
+        // IMMEDIATE 事务开始时即取得写锁，检查修订号和写入之间不会有其它写入
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(write_error)?;
+        let current = revision(&tx).map_err(write_error)?;
+        if self.revision.is_some_and(|r| r != current) {
+            return Err(Error::VaultChangedExternally);
+        }
+        write(&tx)?;
+        let next = current.wrapping_add(1);
+        tx.pragma_update(None, "user_version", next)
+            .map_err(write_error)?;
+        tx.commit().map_err(write_error)?;
+        self.revision = Some(next);
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    fn read(&self) -> Result<StorageData, Error> {
+        let data: Option<String> = self
+            .connect(false)?
+            .query_row("SELECT data FROM vault WHERE id = 0", [], |row| row.get(0))
+            .optional()
+            .map_err(Error::corrupt)?;
+        parse_storage_data(&data.ok_or_else(|| Error::corrupt("missing vault header"))?)
+    }
+
+    // 先取修订号再读取，读取期间发生的修改也能被发现
+    fn read_synced(&mut self) -> Result<StorageData, Error> {
+        let current = revision(&self.connect(false)?).map_err(Error::corrupt)?;
+        let data = self.read()?;
+        self.revision = Some(current);
+        Ok(data)
+    }
+
+    fn write(&mut self, data: &StorageData) -> Result<(), Error> {
+        self.transact(|tx| put_header(tx, data))
+    }
+
+    // 删除数据库文件以及 SQLite 的日志文件
+    fn wipe(&mut self) -> Result<(), Error> {
+        self.release();
+        for path in [
+            self.path.clone(),
+            sibling_file(&self.path, "journal"),
+            sibling_file(&self.path, "wal"),
+            sibling_file(&self.path, "shm"),
+        ] {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|e| Error::io("Failed to wipe vault", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn acquire_lock(&mut self) -> Result<(), Error> {
+        if self.lock.is_none() {
+            self.lock = Some(VaultLock::acquire(&self.path)?);
+        }
+        Ok(())
+    }
+
+    fn release(&mut self) {
+        self.lock = None;
+        self.revision = None;
+    }
+
+    // 数据库被删除或无法读取时也算作被修改
+    fn changed_externally(&self) -> bool {
+        match self.revision {
+            Some(known) => {
+                let current = self
+                    .connect(false)
+                    .and_then(|c| revision(&c).map_err(Error::corrupt));
+                current.ok() != Some(known)
+            }
+            None => false,
+        }
+    }
+
+    fn stores_records(&self) -> bool {
+        true
+    }
+
+    fn read_records(&self) -> Result<Vec<EncryptedRecord>, Error> {
+        let conn = self.connect(false)?;
+        // 按插入顺序返回，更新记录不改变 rowid
+        let mut statement = conn
+            .prepare("SELECT kind, id, encrypted_data, nonce FROM records ORDER BY rowid")
+            .map_err(Error::corrupt)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                ))
+            })
+            .map_err(Error::corrupt)?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            let (kind, id, encrypted_data, nonce) = row.map_err(Error::corrupt)?;
+            let kind = RecordKind::parse(&kind)
+                .ok_or_else(|| Error::corrupt(format!("unknown record kind {:?}", kind)))?;
+            records.push(EncryptedRecord {
+                kind,
+                id,
+                encrypted_data,
+                nonce,
+            });
+        }
+        Ok(records)
+    }
+
+    fn write_records(
+        &mut self,
+        put: &[EncryptedRecord],
+        delete: &[(RecordKind, String)],
+    ) -> Result<(), Error> {
+        self.transact(|tx| {
+            put_records(tx, put)?;
+            delete_records(tx, delete)
+        })
+    }
+
+    fn write_all_records(
+        &mut self,
+        data: &StorageData,
+        records: &[EncryptedRecord],
+    ) -> Result<(), Error> {
+        self.transact(|tx| {
+            put_header(tx, data)?;
+            put_records(tx, records)?;
+
+            // 已更新的记录保留原来的 rowid（读取顺序不变），只删除不再存在的记录
+            let stale: Vec<(RecordKind, String)> = {
+                let mut statement = tx
+                    .prepare("SELECT kind, id FROM records")
+                    .map_err(write_error)?;
+                let rows = statement
+                    .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))
+                    .map_err(write_error)?;
+                let mut stale = Vec::new();
+                for row in rows {
+                    let (kind, id): (String, String) = row.map_err(write_error)?;
+                    let kind = RecordKind::parse(&kind)
+                        .ok_or_else(|| Error::corrupt(format!("unknown record kind {:?}", kind)))?;
+                    if !records.iter().any(|r| r.kind == kind && r.id == id) {
+                        stale.push((kind, id));
+                    }
+                }
+                stale
+            };
+            delete_records(tx, &stale)
+        })
+    }
+}
+
+fn revision(conn: &Connection) -> rusqlite::Result<i32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+fn write_error(e: rusqlite::Error) -> Error {
+    Error::io("Failed to write vault", e)
+}
+
+fn put_header(conn: &Connection, data: &StorageData) -> Result<(), Error> {
+    conn.execute(
+        "INSERT INTO vault (id, data) VALUES (0, ?1)
+         ON CONFLICT (id) DO UPDATE SET data = excluded.data",
+        [serde_json::to_string(data).unwrap()],
+    )
+    .map_err(write_error)?;
+    Ok(())
+}
+
+fn put_records(conn: &Connection, records: &[EncryptedRecord]) -> Result<(), Error> {
+    for record in records {
+        conn.execute(
+            "INSERT INTO records (kind, id, encrypted_data, nonce) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (kind, id) DO UPDATE
+             SET encrypted_data = excluded.encrypted_data, nonce = excluded.nonce",
+            params![
+                record.kind.as_str(),
+                record.id,
+                record.encrypted_data,
+                record.nonce
+            ],
+        )
+        .map_err(write_error)?;
+    }
+    Ok(())
+}
+
+fn delete_records(conn: &Connection, delete: &[(RecordKind, String)]) -> Result<(), Error> {
+    for (kind, id) in delete {
+        conn.execute(
+            "DELETE FROM records WHERE kind = ?1 AND id = ?2",
+            params![kind.as_str(), id],
+        )
+        .map_err(write_error)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+// 存储后端：Vault 只通过这个 trait 读写加密后的保险库数据，数据保存在哪里由实现决定
+use crate::{Error, FileStorage, SqliteStorage, StorageData};
+use std::path::Path;
+
+pub trait Storage: Send {
+    /// 显示给用户的保险库位置（文件路径等）
+    fn location(&self) -> String;
+
+    fn exists(&self) -> bool;
+
+    fn read(&self) -> Result<StorageData, Error>;
+
+    /// 读取并记下此时的状态，之后的 write 据此拒绝覆盖其它程序的修改
+    fn read_synced(&mut self) -> Result<StorageData, Error> {
+        self.read()
+    }
+
+    fn write(&mut self, data: &StorageData) -> Result<(), Error>;
+
+    /// 删除保险库数据及其备份
+    fn wipe(&mut self) -> Result<(), Error>;
+
+    /// 导出为保险库文件格式的 JSON。按记录保存的后端由 Vault 把所有记录加密进 StorageData 后导出
+    fn export(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(&self.read()?).unwrap())
+    }
+
+    /// 解锁（读写）期间独占保险库，其它进程无法同时打开
+    fn acquire_lock(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// 锁定保险库时释放独占锁，并丢弃 read_synced 记下的状态
+    fn release(&mut self) {}
+
+    /// 上次 read_synced 或 write 之后是否被其它程序修改过
+    fn changed_externally(&self) -> bool {
+        false
+    }
+
+    /// 写入前保留的备份数量，不支持备份的后端忽略
+    fn set_backup_retention(&mut self, _retention: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// 是否按记录保存：每个条目和分组单独加密成一条记录，修改一条只需写入这一条。
+    /// 这时 StorageData 中只加密保存空的 AppData，用来校验密钥
+    fn stores_records(&self) -> bool {
+        false
+    }
+
+    fn read_records(&self) -> Result<Vec<EncryptedRecord>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// 在同一个事务中写入 put 并删除 delete 中的记录
+    fn write_records(
+        &mut self,
+        _put: &[EncryptedRecord],
+        _delete: &[(RecordKind, String)],
+    ) -> Result<(), Error> {
+        Err(Error::invalid_input(
+            "storage",
+            "this backend does not store records",
+        ))
+    }
+
+    /// 在同一个事务中写入 data，并把所有记录替换为 records（不在其中的记录被删除）。
+    /// 用于更换密钥或重写整个保险库，header 和记录不会一个用新密钥、一个用旧密钥
+    fn write_all_records(
+        &mut self,
+        _data: &StorageData,
+        _records: &[EncryptedRecord],
+    ) -> Result<(), Error> {
+        Err(Error::invalid_input(
+            "storage",
+            "this backend does not store records",
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    Entry,
+    Group,
+}
+
+impl RecordKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Entry => "entry",
+            Self::Group => "group",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "entry" => Some(Self::Entry),
+            "group" => Some(Self::Group),
+            _ => None,
+        }
+    }
+}
+
+/// 用 vault key 单独加密的一个条目或分组
+#[derive(Debug, Clone)]
+pub struct EncryptedRecord {
+    pub kind: RecordKind,
+    pub id: String,
+    pub encrypted_data: String,
+    pub nonce: String,
+}
+
+/// 按扩展名选择存储后端：.db / .sqlite 使用 SQLite，其它使用单个 JSON 文件
+pub fn open_storage(path: &Path, backup_retention: usize) -> Box<dyn Storage> {
+    if is_sqlite_path(path) {
+        Box::new(SqliteStorage::new(path))
+    } else {
+        Box::new(FileStorage::new(path, backup_retention))
+    }
+}
+
+/// 该路径的保险库是否使用 SQLite 后端（SQLite 后端不生成 .bak 备份）
+pub fn is_sqlite_path(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("db" | "sqlite" | "sqlite3")
+    )
+}
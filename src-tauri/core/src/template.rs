@@ -0,0 +1,155 @@
+// 配置文件模板：把 {{ 2pass://<分组>/<条目>/<字段> }} 替换成保险库中的值。
+// 只处理内容是 2pass:// 引用的占位符，其它 {{ ... }} 原样保留，模板可以同时使用 Helm、Jinja 等自己的语法
+use crate::{Error, PasswordEntry, PasswordGroup, SecretReference, SecretString};
+use serde::Serialize;
+use std::ops::Range;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+/// 模板中的一个引用占位符
+pub struct Placeholder<'a> {
+    /// 整个 {{ ... }} 在模板中的字节范围
+    pub range: Range<usize>,
+    /// 去掉首尾空白后的引用
+    pub reference: &'a str,
+    /// 所在行，从 1 开始
+    pub line: usize,
+}
+
+/// 模板用到的一个引用及其对应的条目，不含字段的值，供预览和报告无法解析的引用
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateReference {
+    pub reference: String,
+    pub line: usize,
+    pub entry_id: Option<String>,
+    pub entry_title: Option<String>,
+    pub field: Option<String>,
+    pub error: Option<Error>,
+}
+
+/// 渲染结果；unresolved 中的占位符在 output 里保持原样
+pub struct Rendered {
+    pub output: SecretString,
+    pub references: usize,
+    pub unresolved: Vec<TemplateReference>,
+}
+
+/// 按出现顺序找出模板中的引用占位符
+pub fn placeholders(template: &str) -> Vec<Placeholder<'_>> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while let Some(open) = template[pos..].find(OPEN) {
+        let start = pos + open;
+        let Some(close) = template[start + OPEN.len()..].find(CLOSE) else {
+            break;
+        };
+        let end = start + OPEN.len() + close + CLOSE.len();
+        let inner = template[start + OPEN.len()..end - CLOSE.len()].trim();
+        if SecretReference::is_reference(inner) {
+            found.push(Placeholder {
+                range: start..end,
+                reference: inner,
+                line: template[..start].matches('\n').count() + 1,
+            });
+            pos = end;
+        } else {
+            // 不是引用，跳过 "{{" 继续找，内部可能还有引用
+            pos = start + OPEN.len();
+        }
+    }
+    found
+}
+
+// 解析引用并找到条目，结果记录在 TemplateReference 中
+fn lookup(
+    placeholder: &Placeholder,
+    entries: &[PasswordEntry],
+    groups: &[PasswordGroup],
+) -> (TemplateReference, Option<SecretReference>) {
+    let mut usage = TemplateReference {
+        reference: placeholder.reference.to_string(),
+        line: placeholder.line,
+        entry_id: None,
+        entry_title: None,
+        field: None,
+        error: None,
+    };
+    let found = placeholder
+        .reference
+        .parse::<SecretReference>()
+        .and_then(|reference| {
+            usage.field = Some(reference.field.to_string());
+            let entry = reference.find_entry(entries, groups)?;
+            usage.entry_id = Some(entry.id.clone());
+            usage.entry_title = Some(entry.title.clone());
+            Ok(reference)
+        });
+    match found {
+        Ok(found) => (usage, Some(found)),
+        Err(e) => {
+            usage.error = Some(e);
+            (usage, None)
+        }
+    }
+}
+
+/// 列出模板用到的引用和对应的条目，不读取字段的值
+pub fn preview(
+    template: &str,
+    entries: &[PasswordEntry],
+    groups: &[PasswordGroup],
+) -> Vec<TemplateReference> {
+    placeholders(template)
+        .iter()
+        .map(|placeholder| lookup(placeholder, entries, groups).0)
+        .collect()
+}
+
+/// 替换模板中的引用。无法解析的引用保持原样并记录在 unresolved 中，由调用方决定是否视为错误
+pub fn render(template: &str, entries: &[PasswordEntry], groups: &[PasswordGroup]) -> Rendered {
+    let placeholders = placeholders(template);
+    let mut unresolved = Vec::new();
+    let values: Vec<Option<SecretString>> = placeholders
+        .iter()
+        .map(|placeholder| {
+            let (mut usage, found) = lookup(placeholder, entries, groups);
+            let value = match found {
+                Some(reference) => reference.resolve(entries, groups),
+                None => Err(usage.error.take().unwrap()),
+            };
+            match value {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    usage.error = Some(e);
+                    unresolved.push(usage);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    // 先算出总长度一次分配好，避免扩容时在旧内存里留下明文
+    let len = template.len()
+        + values
+            .iter()
+            .flatten()
+            .map(|value| value.len())
+            .sum::<usize>();
+    let mut output = String::with_capacity(len);
+    let mut pos = 0;
+    for (placeholder, value) in placeholders.iter().zip(&values) {
+        output.push_str(&template[pos..placeholder.range.start]);
+        match value {
+            Some(value) => output.push_str(value),
+            None => output.push_str(&template[placeholder.range.clone()]),
+        }
+        pos = placeholder.range.end;
+    }
+    output.push_str(&template[pos..]);
+    Rendered {
+        output: SecretString::new(output),
+        references: placeholders.len(),
+        unresolved,
+    }
+}
@@ -0,0 +1,102 @@
+// TOTP（RFC 6238）：6 位数字，30 秒一个周期，HMAC-SHA1
+use crate::Error;
+use data_encoding::{BASE32, BASE32_NOPAD};
+use rand::RngCore;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const PERIOD_SECS: u64 = 30;
+const DIGITS: u32 = 6;
+
+/// 当前时间的验证码
+pub fn generate(secret: &str) -> Result<String, Error> {
+    // Get current timestamp
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| Error::io("System time error", e))?
+        .as_secs();
+    generate_at(secret, timestamp)
+}
+
+pub fn generate_at(secret: &str, timestamp: u64) -> Result<String, Error> {
+    let secret_bytes = decode_secret(secret)?;
+    let totp =
+        totp_lite::totp_custom::<totp_lite::Sha1>(PERIOD_SECS, DIGITS, &secret_bytes, timestamp);
+
+    Ok(format!("{:06}", totp))
+}
+
+fn decode_secret(secret: &str) -> Result<Vec<u8>, Error> {
+    // Remove any whitespace and padding characters, convert to uppercase
+    let clean_secret = secret.replace(" ", "").replace("=", "").to_uppercase();
+
+    // Validate Base32 characters
+    for (i, c) in clean_secret.chars().enumerate() {
+        if !c.is_ascii_uppercase() && !('2'..='7').contains(&c) {
+            return Err(Error::InvalidTotpSecret {
+                reason: format!(
+                    "invalid character '{}' at position {}, Base32 only allows A-Z and 2-7",
+                    c, i
+                ),
+            });
+        }
+    }
+
+    // Try to decode base32 secret
+    // First try without padding (most common for TOTP)
+    let secret_bytes = BASE32_NOPAD
+        .decode(clean_secret.as_bytes())
+        .or_else(|e1| {
+            // If that fails, try adding padding
+            let padded = add_base32_padding(&clean_secret);
+            BASE32.decode(padded.as_bytes()).map_err(|e2| {
+                format!(
+                    "Both decode attempts failed. NOPAD: {:?}, PADDED: {:?}",
+                    e1, e2
+                )
+            })
+        })
+        .map_err(|e| Error::InvalidTotpSecret { reason: e })?;
+
+    if secret_bytes.is_empty() {
+        return Err(Error::InvalidTotpSecret {
+            reason: "secret is empty".to_string(),
+        });
+    }
+
+    Ok(secret_bytes)
+}
+
+// Helper function to add Base32 padding if needed
+fn add_base32_padding(s: &str) -> String {
+    let remainder = s.len() % 8;
+    if remainder == 0 {
+        s.to_string()
+    } else {
+        let padding_needed = 8 - remainder;
+        format!("{}{}", s, "=".repeat(padding_needed))
+    }
+}
+
+pub fn generate_secret() -> String {
+    // Generate 20 random bytes (160 bits) for TOTP secret
+    let mut secret = vec![0u8; 20];
+    rand::thread_rng().fill_bytes(&mut secret);
+
+    // Encode to base32 with padding (standard format)
+    // 20 bytes = 160 bits, Base32 encodes 5 bits per character
+    // 160 / 5 = 32 characters, padded to 40 with '='
+    BASE32.encode(&secret)
+}
+
+pub fn qr_url(secret: &str, account_name: &str, issuer: &str) -> String {
+    // Remove padding for the QR code URL (standard practice)
+    let clean_secret = secret.replace("=", "");
+
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}",
+        urlencoding::encode(issuer),
+        urlencoding::encode(account_name),
+        clean_secret,
+        urlencoding::encode(issuer)
+    )
+}
@@ -0,0 +1,67 @@
+// 本机 Unix socket：只允许当前用户连接。socket 文件权限为 0600，
+// 每个连接再检查对端进程的 uid（SO_PEERCRED / getpeereid），防止其它用户通过放宽的目录权限连接
+use crate::Error;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+/// 监听 path。已有进程在监听时返回 None；上次异常退出留下的 socket 文件会被删除
+pub fn bind_private(path: &Path) -> Result<Option<UnixListener>, Error> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Ok(None);
+        }
+        fs::remove_file(path).map_err(|e| Error::io("Failed to remove stale socket", e))?;
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| Error::io("Failed to create socket directory", e))?;
+    }
+    let listener = UnixListener::bind(path).map_err(|e| Error::io("Failed to bind socket", e))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .map_err(|e| Error::io("Failed to restrict socket permissions", e))?;
+    Ok(Some(listener))
+}
+
+/// 对端进程是否属于当前用户
+pub fn peer_is_current_user(stream: &UnixStream) -> io::Result<bool> {
+    // SAFETY: geteuid 没有前置条件
+    Ok(peer_uid(stream)? == unsafe { libc::geteuid() })
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<libc::uid_t> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: cred 和 len 在调用期间有效，len 是 cred 的大小
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> io::Result<libc::uid_t> {
+    let mut uid = 0;
+    let mut gid = 0;
+    // SAFETY: uid 和 gid 在调用期间有效
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
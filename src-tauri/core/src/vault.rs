@@ -0,0 +1,585 @@
+// 保险库：通过存储后端读写加密数据，解锁后在内存中保存 vault key 和解密后的条目、分组。
+// 所有修改都立即加密并写回存储后端
+use crate::crypto::{decrypt_data, encrypt_data, to_secret_json};
+use crate::format::{
+    open_vault, upgrade_to_key_slot, AppData, AppDataRef, VaultHeader, CURRENT_FORMAT_VERSION,
+};
+use crate::import::{open_encrypted_export, parse_chrome_csv};
+use crate::keyslot::{self, KeySlot, KeySlotInfo, KeySlotKind, UnlockSecret};
+use crate::{
+    EncryptedRecord, Error, PasswordEntry, PasswordGroup, RecordKind, SecretKey, SecretString,
+    Storage, StorageData,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashSet;
+use zeroize::Zeroize;
+
+pub struct Vault {
+    storage: Box<dyn Storage>,
+    key: Option<SecretKey>,
+    unlocked_with: Option<KeySlotKind>, // 本次解锁使用的 slot 类型
+    read_only: bool,                    // 只读打开：拒绝所有修改，不写入存储
+    entries: Vec<PasswordEntry>,
+    groups: Vec<PasswordGroup>,
+}
+
+impl Vault {
+    pub fn new(storage: Box<dyn Storage>) -> Self {
+        Self {
+            storage,
+            key: None,
+            unlocked_with: None,
+            read_only: false,
+            entries: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn storage(&self) -> &dyn Storage {
+        self.storage.as_ref()
+    }
+
+    pub fn exists(&self) -> bool {
+        self.storage.exists()
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.key.is_some()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn unlocked_with(&self) -> Option<KeySlotKind> {
+        self.unlocked_with
+    }
+
+    fn key(&self) -> Result<&SecretKey, Error> {
+        self.key.as_ref().ok_or(Error::NotAuthenticated)
+    }
+
+    // 修改前检查：已解锁且不是只读打开
+    fn check_writable(&self) -> Result<(), Error> {
+        self.key()?;
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        Ok(())
+    }
+
+    // 创建只有一个主密码 slot 的空保险库，并保持解锁
+    pub fn create(&mut self, master_password: &str, key_file: Option<&[u8]>) -> Result<(), Error> {
+        self.storage.acquire_lock()?;
+        let key = SecretKey::random();
+        let password_slot = KeySlot::password(
+            keyslot::MASTER_PASSWORD_LABEL,
+            master_password,
+            key_file,
+            &key,
+        )?;
+        let empty_data = serde_json::to_string(&AppData::default()).unwrap();
+        let (encrypted_data, nonce) = encrypt_data(empty_data.as_bytes(), &key)?;
+
+        let storage_data = StorageData {
+            header: Some(VaultHeader::new(vec![password_slot])),
+            master_password_hash: None,
+            encrypted_data,
+            nonce,
+        };
+
+        self.write(&storage_data)?;
+
+        self.key = Some(key);
+        self.unlocked_with = Some(KeySlotKind::Password);
+        self.entries = Vec::new();
+        self.groups = Vec::new();
+
+        Ok(())
+    }
+
+    // 凭据错误时返回 Ok(false)；主密码绑定了密钥文件而没有提供或提供错误时返回 Err，以便和密码错误区分
+    pub fn unlock(&mut self, secret: UnlockSecret, read_only: bool) -> Result<bool, Error> {
+        // 只读打开不获取锁，其它进程仍可正常打开这个保险库
+        self.read_only = read_only;
+        if !read_only {
+            self.storage.acquire_lock()?;
+        }
+        let result = self.try_unlock(secret);
+        // 没有解锁成功时释放锁，让其它进程可以打开
+        if self.key.is_none() {
+            self.storage.release();
+            self.read_only = false;
+        }
+        result
+    }
+
+    fn try_unlock(&mut self, secret: UnlockSecret) -> Result<bool, Error> {
+        let mut storage_data = self.storage.read_synced()?;
+
+        // 每个适用的 slot 运行一次 KDF，能否解开 vault key 并解密保险库即说明凭据是否正确
+        let Some((key, unlocked_with)) = storage_data.unlock_data_key(&secret)? else {
+            return Ok(false);
+        };
+        let Some(data) = self.open_data(&storage_data, &key)? else {
+            return Ok(false);
+        };
+
+        // 旧版本的保险库在解锁时写回当前格式（只读打开时保持原样）
+        let format_version = storage_data.format_version();
+        let (key, migrated_header) =
+            if format_version != Some(CURRENT_FORMAT_VERSION) && !self.read_only {
+                let (key, password_slot) =
+                    upgrade_to_key_slot(storage_data.header.take(), key, &secret)?;
+                (key, Some(VaultHeader::new(vec![password_slot])))
+            } else {
+                (key, None)
+            };
+
+        // 升级生成了新的 vault key，必须先把包装它的 header 写入磁盘再算解锁成功：
+        // 写入失败时保持锁定，否则之后的保存会用磁盘上没有 header 能解开的密钥加密数据
+        if let Some(header) = migrated_header {
+            storage_data.header = Some(header);
+            storage_data.master_password_hash = None;
+            let written = write_vault(
+                self.storage.as_mut(),
+                &mut storage_data,
+                &key,
+                &data.entries,
+                &data.groups,
+            );
+            if let Err(e) = written {
+                let mut data = data;
+                data.entries.zeroize();
+                data.groups.zeroize();
+                return Err(e);
+            }
+            eprintln!(
+                "🔑 Migrated vault from format v{} to v{}",
+                format_version.unwrap_or(2),
+                CURRENT_FORMAT_VERSION
+            );
+        }
+
+        self.entries = data.entries;
+        self.groups = data.groups;
+        self.key = Some(key);
+        self.unlocked_with = Some(unlocked_with);
+
+        Ok(true)
+    }
+
+    // 清零密钥和所有解密后的数据
+    pub fn lock(&mut self) {
+        // SecretKey 在 drop 时清零
+        self.key = None;
+        self.unlocked_with = None;
+        self.read_only = false;
+        self.storage.release();
+        self.entries.zeroize();
+        self.groups.zeroize();
+    }
+
+    // 用当前的 vault key 重新读取存储；数据换成了别的保险库时锁定，需要重新解锁
+    pub fn reload(&mut self) -> Result<(), Error> {
+        self.key()?;
+        let storage_data = self.storage.read_synced()?;
+        let Some(data) = self.open_data(&storage_data, self.key()?)? else {
+            self.lock();
+            return Err(Error::VaultReplaced);
+        };
+
+        self.entries = data.entries;
+        self.groups = data.groups;
+        Ok(())
+    }
+
+    pub fn changed_externally(&self) -> bool {
+        self.storage.changed_externally()
+    }
+
+    // 删除保险库数据及其备份，同时锁定
+    pub fn wipe(&mut self) -> Result<(), Error> {
+        self.lock();
+        self.storage.wipe()
+    }
+
+    pub fn set_backup_retention(&mut self, retention: usize) -> Result<(), Error> {
+        self.storage.set_backup_retention(retention)
+    }
+
+    pub fn entries(&self) -> Result<&[PasswordEntry], Error> {
+        self.key()?;
+        Ok(&self.entries)
+    }
+
+    pub fn groups(&self) -> Result<&[PasswordGroup], Error> {
+        self.key()?;
+        Ok(&self.groups)
+    }
+
+    // id 必须是新的：重复的 id 会让后续的修改和删除作用到另一个条目上，按记录保存时还会覆盖它
+    pub fn add_entry(&mut self, entry: PasswordEntry) -> Result<(), Error> {
+        self.check_writable()?;
+        if self.entries.iter().any(|e| e.id == entry.id) {
+            return Err(Error::EntryExists { id: entry.id });
+        }
+        let id = entry.id.clone();
+        self.entries.push(entry);
+        self.save_record(RecordKind::Entry, &id)
+    }
+
+    pub fn update_entry(&mut self, entry: PasswordEntry) -> Result<(), Error> {
+        self.check_writable()?;
+        let Some(pos) = self.entries.iter().position(|e| e.id == entry.id) else {
+            return Err(Error::EntryNotFound { id: entry.id });
+        };
+        let id = entry.id.clone();
+        self.entries[pos] = entry;
+        self.save_record(RecordKind::Entry, &id)
+    }
+
+    pub fn delete_entry(&mut self, id: &str) -> Result<(), Error> {
+        self.check_writable()?;
+        self.entries.retain(|e| e.id != id);
+        self.save_record(RecordKind::Entry, id)
+    }
+
+    pub fn add_group(&mut self, group: PasswordGroup) -> Result<(), Error> {
+        self.check_writable()?;
+        if self.groups.iter().any(|g| g.id == group.id) {
+            return Err(Error::GroupExists { id: group.id });
+        }
+        let id = group.id.clone();
+        self.groups.push(group);
+        self.save_record(RecordKind::Group, &id)
+    }
+
+    pub fn update_group(&mut self, group: PasswordGroup) -> Result<(), Error> {
+        self.check_writable()?;
+        let Some(existing) = self.groups.iter_mut().find(|g| g.id == group.id) else {
+            return Err(Error::GroupNotFound { id: group.id });
+        };
+        let id = group.id.clone();
+        *existing = group;
+        self.save_record(RecordKind::Group, &id)
+    }
+
+    pub fn delete_group(&mut self, id: &str) -> Result<(), Error> {
+        self.check_writable()?;
+        // 检查是否有密码使用此分组
+        if self
+            .entries
+            .iter()
+            .any(|e| e.group_id.as_deref() == Some(id))
+        {
+            return Err(Error::GroupNotEmpty { id: id.to_string() });
+        }
+        self.groups.retain(|g| g.id != id);
+        self.save_record(RecordKind::Group, id)
+    }
+
+    // 导入 Chrome 导出的 CSV，返回导入的条目数
+    pub fn import_chrome_csv(&mut self, csv_content: &str) -> Result<usize, Error> {
+        self.check_writable()?;
+        let imported = parse_chrome_csv(csv_content, self.entries.len())?;
+        let imported_count = imported.len();
+        self.entries.extend(imported);
+        self.save()?;
+        Ok(imported_count)
+    }
+
+    // 合并另一个 2Pass 导出的保险库，跳过 id 已存在的条目和分组，返回导入的条目数
+    pub fn import_encrypted(
+        &mut self,
+        encrypted_json: &str,
+        password: SecretString,
+    ) -> Result<usize, Error> {
+        self.check_writable()?;
+        let import_data = open_encrypted_export(encrypted_json, password)?;
+
+        // 合并密码条目（避免ID冲突）
+        let existing_entry_ids: HashSet<String> =
+            self.entries.iter().map(|e| e.id.clone()).collect();
+
+        let mut imported_count = 0;
+        for mut entry in import_data.entries {
+            if !existing_entry_ids.contains(&entry.id) {
+                entry.sort_order = Some(self.entries.len() as i64);
+                self.entries.push(entry);
+                imported_count += 1;
+            }
+        }
+
+        // 合并分组（避免ID冲突）
+        let existing_group_ids: HashSet<String> =
+            self.groups.iter().map(|g| g.id.clone()).collect();
+
+        for mut group in import_data.groups {
+            if !existing_group_ids.contains(&group.id) {
+                group.sort_order = self.groups.len() as i64;
+                self.groups.push(group);
+            }
+        }
+
+        self.save()?;
+        Ok(imported_count)
+    }
+
+    // 导出加密的保险库文件，可以用主密码在其它设备上导入
+    pub fn export(&self) -> Result<String, Error> {
+        let key = self.key()?;
+        if !self.storage.stores_records() {
+            return self.storage.export();
+        }
+
+        // 按记录保存的后端导出为和文件后端相同的格式：所有条目和分组加密在一起
+        let mut storage_data = self.storage.read()?;
+        let app_data = AppDataRef {
+            entries: &self.entries,
+            groups: &self.groups,
+        };
+        let data_json = to_secret_json(&app_data);
+        let (encrypted_data, nonce) = encrypt_data(data_json.as_bytes(), key)?;
+        storage_data.encrypted_data = encrypted_data;
+        storage_data.nonce = nonce;
+        Ok(serde_json::to_string(&storage_data).unwrap())
+    }
+
+    // 需要先解锁；和 unlock 一样旧密码错误时返回 Ok(false)，由调用方通过 attempts::throttled 计入失败次数
+    pub fn change_master_password(
+        &mut self,
+        old_password: SecretString,
+        new_password: &str,
+        key_file: Option<zeroize::Zeroizing<Vec<u8>>>,
+    ) -> Result<bool, Error> {
+        self.check_writable()?;
+
+        // 读取当前存储数据
+        let mut storage_data = self.storage.read()?;
+
+        // 旧格式在解锁时就会升级，这里只处理带 key slot 的保险库
+        let header = storage_data
+            .header
+            .as_mut()
+            .filter(|h| h.format_version == CURRENT_FORMAT_VERSION)
+            .ok_or(Error::VaultNotUpgraded)?;
+
+        // 找到旧密码能解开的主密码 slot，找不到即旧密码错误
+        let old_secret = UnlockSecret::Password {
+            password: old_password,
+            key_file,
+        };
+        let unlocked =
+            keyslot::unlock_slots(&header.key_slots, &old_secret, Some(KeySlotKind::Password))?;
+        let Some((index, vault_key)) = unlocked else {
+            return Ok(false);
+        };
+
+        // 只需用新密码派生的 KEK 重新包装这个 slot 的 vault key（同时更换盐值），
+        // 数据和其他 slot 都不用改动；绑定的密钥文件保持不变
+        let slot = &mut header.key_slots[index];
+        let key_file = old_secret
+            .key_file()
+            .filter(|_| slot.key_file_check.is_some());
+        slot.rewrap_password(new_password, key_file, &vault_key)?;
+
+        self.write(&storage_data)?;
+        Ok(true)
+    }
+
+    pub fn key_slots(&self) -> Result<Vec<KeySlotInfo>, Error> {
+        self.key()?;
+        let storage_data = self.storage.read()?;
+        Ok(storage_data
+            .header
+            .map(|h| h.key_slots.iter().map(KeySlot::info).collect())
+            .unwrap_or_default())
+    }
+
+    // 读取 header，修改 key slot 后写回
+    pub(crate) fn update_key_slots<T>(
+        &mut self,
+        update: impl FnOnce(&mut Vec<KeySlot>, &SecretKey) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        self.check_writable()?;
+
+        let mut storage_data = self.storage.read()?;
+        let header = storage_data
+            .header
+            .as_mut()
+            .filter(|h| !h.key_slots.is_empty())
+            .ok_or(Error::VaultNotUpgraded)?;
+
+        let result = update(&mut header.key_slots, self.key()?)?;
+        self.write(&storage_data)?;
+        Ok(result)
+    }
+
+    // 解密数据；按记录保存的后端再逐条解密条目和分组。密钥错误时返回 Ok(None)
+    fn open_data(
+        &self,
+        storage_data: &StorageData,
+        key: &SecretKey,
+    ) -> Result<Option<AppData>, Error> {
+        let Some(mut data) = open_vault(storage_data, key)? else {
+            return Ok(None);
+        };
+        if self.storage.stores_records() {
+            for record in self.storage.read_records()? {
+                match record.kind {
+                    RecordKind::Entry => data.entries.push(open_record(&record, key)?),
+                    RecordKind::Group => data.groups.push(open_record(&record, key)?),
+                }
+            }
+        }
+        Ok(Some(data))
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        let mut storage_data = self.storage.read()?;
+        self.write_all(&mut storage_data)
+    }
+
+    // 写回整个保险库：header 和加密数据。按记录保存的后端在同一个事务中写入所有记录，
+    // 并删除已不存在的条目和分组
+    fn write_all(&mut self, storage_data: &mut StorageData) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        let key = self.key.as_ref().ok_or(Error::NotAuthenticated)?;
+        write_vault(
+            self.storage.as_mut(),
+            storage_data,
+            key,
+            &self.entries,
+            &self.groups,
+        )
+    }
+
+    // 保存一个条目或分组的修改（已不存在即为删除）：按记录保存的后端只写入这一条，
+    // 否则重新加密整个保险库
+    fn save_record(&mut self, kind: RecordKind, id: &str) -> Result<(), Error> {
+        if !self.storage.stores_records() {
+            return self.save();
+        }
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+
+        let key = self.key()?;
+        let record = match kind {
+            RecordKind::Entry => self
+                .entries
+                .iter()
+                .find(|e| e.id == id)
+                .map(|e| seal_record(kind, id, e, key)),
+            RecordKind::Group => self
+                .groups
+                .iter()
+                .find(|g| g.id == id)
+                .map(|g| seal_record(kind, id, g, key)),
+        }
+        .transpose()?;
+        match record {
+            Some(record) => self.storage.write_records(&[record], &[]),
+            None => self.storage.write_records(&[], &[(kind, id.to_string())]),
+        }
+    }
+
+    // 用当前密钥加密 entries 和 groups，写入 storage_data。
+    // 按记录保存的后端这里只加密空的 AppData，用来在解锁时校验密钥
+    fn write(&mut self, storage_data: &StorageData) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        self.storage.write(storage_data)
+    }
+}
+
+// 用给定的 vault key 加密并写入整个保险库；不经过 self，解锁时可以在写入成功之后再保存密钥和数据
+fn write_vault(
+    storage: &mut dyn Storage,
+    storage_data: &mut StorageData,
+    key: &SecretKey,
+    entries: &[PasswordEntry],
+    groups: &[PasswordGroup],
+) -> Result<(), Error> {
+    let stores_records = storage.stores_records();
+    // 按记录保存时主数据只是空壳，否则包含 entries 和 groups 的完整数据
+    let app_data = if stores_records {
+        AppDataRef {
+            entries: &[],
+            groups: &[],
+        }
+    } else {
+        AppDataRef { entries, groups }
+    };
+    let data_json = to_secret_json(&app_data);
+    let (encrypted_data, nonce) = encrypt_data(data_json.as_bytes(), key)?;
+    storage_data.encrypted_data = encrypted_data;
+    storage_data.nonce = nonce;
+
+    if !stores_records {
+        return storage.write(storage_data);
+    }
+    let mut records = Vec::with_capacity(entries.len() + groups.len());
+    for entry in entries {
+        records.push(seal_record(RecordKind::Entry, &entry.id, entry, key)?);
+    }
+    for group in groups {
+        records.push(seal_record(RecordKind::Group, &group.id, group, key)?);
+    }
+    storage.write_all_records(storage_data, &records)
+}
+
+// 单独加密一个条目或分组
+fn seal_record<T: Serialize>(
+    kind: RecordKind,
+    id: &str,
+    value: &T,
+    key: &SecretKey,
+) -> Result<EncryptedRecord, Error> {
+    let data_json = to_secret_json(value);
+    let (encrypted_data, nonce) = encrypt_data(data_json.as_bytes(), key)?;
+    Ok(EncryptedRecord {
+        kind,
+        id: id.to_string(),
+        encrypted_data,
+        nonce,
+    })
+}
+
+// 密钥已经由 StorageData 校验过，记录解不开或 id 不符（被替换成别的记录）都说明数据损坏
+fn open_record<T: DeserializeOwned + HasId>(
+    record: &EncryptedRecord,
+    key: &SecretKey,
+) -> Result<T, Error> {
+    let decrypted = decrypt_data(&record.encrypted_data, &record.nonce, key)?
+        .ok_or_else(|| Error::corrupt(format!("record {} failed to decrypt", record.id)))?;
+    let value: T = serde_json::from_str(&decrypted).map_err(Error::corrupt)?;
+    if value.id() != record.id {
+        return Err(Error::corrupt(format!(
+            "record {} has a mismatched id",
+            record.id
+        )));
+    }
+    Ok(value)
+}
+
+trait HasId {
+    fn id(&self) -> &str;
+}
+
+impl HasId for PasswordEntry {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl HasId for PasswordGroup {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,799 @@
+// 本机 API：在数据目录的 api.sock 上以 JSON-RPC 2.0（每行一个请求）提供和前端命令相同的操作，
+// 供脚本和内部工具读取凭据。方法名带版本前缀（v1.get_all_entries），以后的不兼容修改使用新的前缀。
+// 客户端先调用 v1.request_token 申请令牌，用户在桌面应用中批准后才能调用其它方法。
+// 令牌带有权限范围（只读、限定分组），api_clients.json 中只保存令牌的 SHA-256
+use crate::reference::resolve_in;
+use crate::vaults::VaultRegistry;
+use crate::{AppState, Error, PasswordEntry, PasswordGroup, SecretString};
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use twopass_core::keyslot::now_millis;
+use twopass_core::reference::ReferenceField;
+use twopass_core::{totp, write_atomic, SecretReference};
+
+pub const API_VERSION: u32 = 1;
+const METHOD_PREFIX: &str = "v1.";
+const SOCKET_NAME: &str = "api.sock";
+const CLIENTS_FILE: &str = "api_clients.json";
+const TOKEN_PREFIX: &str = "2pass_";
+const TOKEN_BYTES: usize = 32;
+
+// 同时等待批准的申请数上限，防止恶意程序不停弹出确认框
+const MAX_PENDING_REQUESTS: usize = 5;
+// last_used_at 只在内存中更新，最多每隔这么久写一次文件（批准和撤销时总是写入）
+const LAST_USED_SAVE_INTERVAL_MS: i64 = 10 * 60 * 1000;
+
+/// 令牌的权限范围
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiPermissions {
+    pub read_only: bool,
+    pub groups: Option<Vec<String>>, // 只能访问这些分组（id）中的条目，None 表示不限
+}
+
+impl ApiPermissions {
+    fn allows_group(&self, group_id: Option<&str>) -> bool {
+        match &self.groups {
+            None => true,
+            Some(groups) => group_id.is_some_and(|id| groups.iter().any(|g| g == id)),
+        }
+    }
+
+    fn check_write(&self) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::PermissionDenied {
+                reason: "the token is read-only".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    // 分组管理和导出整个保险库需要不限分组的令牌
+    fn check_unrestricted(&self) -> Result<(), Error> {
+        if self.groups.is_some() {
+            return Err(Error::PermissionDenied {
+                reason: "the token is limited to some groups".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// 已批准的客户端，显示在设置页面
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiClient {
+    pub id: String,
+    pub name: String,
+    pub permissions: ApiPermissions,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredClient {
+    #[serde(flatten)]
+    client: ApiClient,
+    token_hash: String,
+}
+
+/// 等待用户批准的令牌申请
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenRequest {
+    pub id: String,
+    pub name: String,
+    pub permissions: ApiPermissions,
+    pub requested_at: i64,
+}
+
+enum RequestState {
+    Pending(TokenRequest),
+    Approved(SecretString), // 令牌只通过 token_status 返回一次
+    Denied,
+}
+
+// 申请只能在发起它的连接上查询结果，其它进程猜到申请 id 也拿不到令牌
+struct PendingRequest {
+    connection: u64,
+    state: RequestState,
+}
+
+/// API 信息，显示在设置页面
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiInfo {
+    pub version: u32,
+    pub socket: Option<PathBuf>, // 不支持 Unix socket 的平台为 None
+    pub clients: Vec<ApiClient>,
+}
+
+pub struct ApiRegistry {
+    socket: PathBuf,
+    file: PathBuf,
+    clients: Vec<StoredClient>,
+    requests: HashMap<String, PendingRequest>,
+    saved_at: i64,
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn generate_token() -> SecretString {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    SecretString::new(format!("{}{}", TOKEN_PREFIX, hex))
+}
+
+impl ApiRegistry {
+    pub(crate) fn load(data_dir: &Path) -> Self {
+        let file = data_dir.join(CLIENTS_FILE);
+        let clients = fs::read_to_string(&file)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            socket: data_dir.join(SOCKET_NAME),
+            file,
+            clients,
+            requests: HashMap::new(),
+            saved_at: now_millis(),
+        }
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(&self.clients)
+            .map_err(|e| Error::io("Failed to serialize API clients", e))?;
+        write_atomic(&self.file, json.as_bytes())?;
+        self.saved_at = now_millis();
+        Ok(())
+    }
+
+    fn authenticate(&mut self, token: &str) -> Result<ApiClient, Error> {
+        let token_hash = hash_token(token);
+        let stored = self
+            .clients
+            .iter_mut()
+            .find(|c| c.token_hash == token_hash)
+            .ok_or(Error::InvalidApiToken)?;
+        let now = now_millis();
+        stored.client.last_used_at = Some(now);
+        let client = stored.client.clone();
+        if now - self.saved_at >= LAST_USED_SAVE_INTERVAL_MS {
+            if let Err(e) = self.save() {
+                eprintln!("❌ Failed to save API clients: {}", e);
+            }
+        }
+        Ok(client)
+    }
+
+    fn pending(&self) -> Vec<TokenRequest> {
+        let mut pending: Vec<_> = self
+            .requests
+            .values()
+            .filter_map(|pending| match &pending.state {
+                RequestState::Pending(request) => Some(request.clone()),
+                _ => None,
+            })
+            .collect();
+        pending.sort_by_key(|r| r.requested_at);
+        pending
+    }
+
+    fn request_token(
+        &mut self,
+        connection: u64,
+        name: String,
+        permissions: ApiPermissions,
+    ) -> Result<TokenRequest, Error> {
+        if name.trim().is_empty() {
+            return Err(Error::invalid_input("name", "a client name is required"));
+        }
+        if self.pending().len() >= MAX_PENDING_REQUESTS {
+            return Err(Error::invalid_input(
+                "request",
+                "too many access requests are waiting for approval",
+            ));
+        }
+        let request = TokenRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            permissions,
+            requested_at: now_millis(),
+        };
+        self.requests.insert(
+            request.id.clone(),
+            PendingRequest {
+                connection,
+                state: RequestState::Pending(request.clone()),
+            },
+        );
+        Ok(request)
+    }
+
+    // 批准后第一次查询时返回令牌，之后这个申请就不存在了。其它连接查询时当作申请不存在
+    fn token_status(&mut self, connection: u64, request_id: &str) -> Result<Value, Error> {
+        let not_found = || Error::ApiRequestNotFound {
+            id: request_id.to_string(),
+        };
+        let pending = self
+            .requests
+            .get(request_id)
+            .filter(|p| p.connection == connection)
+            .ok_or_else(not_found)?;
+        if let RequestState::Pending(_) = pending.state {
+            return Ok(json!({ "status": "pending" }));
+        }
+        match self
+            .requests
+            .remove(request_id)
+            .ok_or_else(not_found)?
+            .state
+        {
+            RequestState::Approved(token) => Ok(json!({ "status": "approved", "token": token })),
+            _ => Ok(json!({ "status": "denied" })),
+        }
+    }
+
+    // 连接断开后它的申请无法再取回结果：删除这些申请，已批准但没取走令牌的客户端也一并删除
+    fn drop_connection(&mut self, connection: u64) {
+        let mut orphaned = Vec::new();
+        self.requests.retain(|id, pending| {
+            if pending.connection != connection {
+                return true;
+            }
+            if let RequestState::Approved(_) = pending.state {
+                orphaned.push(id.clone());
+            }
+            false
+        });
+        if !orphaned.is_empty() {
+            self.clients.retain(|c| !orphaned.contains(&c.client.id));
+            if let Err(e) = self.save() {
+                eprintln!("❌ Failed to save API clients: {}", e);
+            }
+        }
+    }
+
+    fn approve(&mut self, request_id: &str) -> Result<ApiClient, Error> {
+        let Some(PendingRequest {
+            connection,
+            state: RequestState::Pending(request),
+        }) = self.requests.get(request_id)
+        else {
+            return Err(Error::ApiRequestNotFound {
+                id: request_id.to_string(),
+            });
+        };
+        let connection = *connection;
+        let client = ApiClient {
+            id: request.id.clone(),
+            name: request.name.clone(),
+            permissions: request.permissions.clone(),
+            created_at: now_millis(),
+            last_used_at: None,
+        };
+        let token = generate_token();
+        self.clients.push(StoredClient {
+            client: client.clone(),
+            token_hash: hash_token(&token),
+        });
+        self.save()?;
+        self.requests.insert(
+            request_id.to_string(),
+            PendingRequest {
+                connection,
+                state: RequestState::Approved(token),
+            },
+        );
+        Ok(client)
+    }
+
+    fn deny(&mut self, request_id: &str) -> Result<(), Error> {
+        match self.requests.get_mut(request_id) {
+            Some(pending) if matches!(pending.state, RequestState::Pending(_)) => {
+                pending.state = RequestState::Denied;
+                Ok(())
+            }
+            _ => Err(Error::ApiRequestNotFound {
+                id: request_id.to_string(),
+            }),
+        }
+    }
+
+    fn revoke(&mut self, client_id: &str) -> Result<(), Error> {
+        self.clients.retain(|c| c.client.id != client_id);
+        self.save()
+    }
+}
+
+#[tauri::command]
+pub fn get_api_info(registry: tauri::State<Mutex<ApiRegistry>>) -> ApiInfo {
+    let registry = registry.lock().unwrap();
+    ApiInfo {
+        version: API_VERSION,
+        socket: cfg!(unix).then(|| registry.socket.clone()),
+        clients: registry.clients.iter().map(|c| c.client.clone()).collect(),
+    }
+}
+
+#[tauri::command]
+pub fn get_pending_api_requests(registry: tauri::State<Mutex<ApiRegistry>>) -> Vec<TokenRequest> {
+    registry.lock().unwrap().pending()
+}
+
+#[tauri::command]
+pub fn approve_api_request(
+    id: String,
+    state: tauri::State<Mutex<AppState>>,
+    registry: tauri::State<Mutex<ApiRegistry>>,
+) -> Result<ApiClient, Error> {
+    // 和安全设置一样只能在解锁后批准，不能只依赖前端的判断
+    if !state.lock().unwrap().vault.is_unlocked() {
+        return Err(Error::NotAuthenticated);
+    }
+    let client = registry.lock().unwrap().approve(&id)?;
+    // 名称由客户端提供，按 Debug 格式输出，避免控制字符伪造日志行
+    println!(
+        "🔌 API access granted to client {} ({:?})",
+        client.id, client.name
+    );
+    Ok(client)
+}
+
+#[tauri::command]
+pub fn deny_api_request(
+    id: String,
+    registry: tauri::State<Mutex<ApiRegistry>>,
+) -> Result<(), Error> {
+    registry.lock().unwrap().deny(&id)
+}
+
+#[tauri::command]
+pub fn revoke_api_client(
+    id: String,
+    registry: tauri::State<Mutex<ApiRegistry>>,
+) -> Result<(), Error> {
+    registry.lock().unwrap().revoke(&id)?;
+    println!("🔌 API access revoked for client {}", id);
+    Ok(())
+}
+
+// JSON-RPC 2.0 的错误。API 的业务错误使用 -32000，data 中是和前端命令相同的 { code, details }
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(e: Error) -> Self {
+        Self {
+            code: -32000,
+            message: e.to_string(),
+            data: Some(serde_json::to_value(&e).unwrap()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    id: Option<Value>, // 没有 id 的通知不返回响应
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+fn params<T: DeserializeOwned>(params: &Value) -> Result<T, RpcError> {
+    serde_json::from_value(params.clone())
+        .map_err(|e| RpcError::new(-32602, format!("Invalid params: {}", e)))
+}
+
+fn to_value(value: impl Serialize) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(value).unwrap())
+}
+
+#[derive(Deserialize)]
+struct TokenParams {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct RequestTokenParams {
+    name: String,
+    #[serde(default)]
+    permissions: ApiPermissions,
+}
+
+#[derive(Deserialize)]
+struct RequestIdParams {
+    request_id: String,
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct EntryParams {
+    entry: PasswordEntry,
+}
+
+#[derive(Deserialize)]
+struct GroupParams {
+    group: PasswordGroup,
+}
+
+#[derive(Deserialize)]
+struct ReferenceParams {
+    reference: String,
+}
+
+#[derive(Deserialize)]
+struct EntryReferenceParams {
+    id: String,
+    field: String,
+}
+
+#[derive(Deserialize)]
+struct SecretParams {
+    secret: String,
+}
+
+// 处理 connection 连接上的一个请求。除申请令牌外的方法都需要 params.token
+fn dispatch(
+    app_handle: &tauri::AppHandle,
+    connection: u64,
+    method: &str,
+    raw: &Value,
+) -> Result<Value, RpcError> {
+    let not_found = || RpcError::new(-32601, format!("Method not found: {}", method));
+    let method = method.strip_prefix(METHOD_PREFIX).ok_or_else(not_found)?;
+    let registry = app_handle.state::<Mutex<ApiRegistry>>();
+
+    match method {
+        "request_token" => {
+            let RequestTokenParams { name, permissions } = params(raw)?;
+            let permissions = resolve_groups(app_handle, permissions)?;
+            let request = registry
+                .lock()
+                .unwrap()
+                .request_token(connection, name, permissions)?;
+            println!("🔌 API access requested by {:?}", request.name);
+            if let Err(e) = app_handle.emit("api-token-requested", &request) {
+                eprintln!("❌ Failed to emit api-token-requested event: {}", e);
+            }
+            return to_value(json!({ "request_id": request.id }));
+        }
+        "token_status" => {
+            let RequestIdParams { request_id } = params(raw)?;
+            return Ok(registry
+                .lock()
+                .unwrap()
+                .token_status(connection, &request_id)?);
+        }
+        _ => {}
+    }
+
+    let TokenParams { token } = params(raw)?;
+    let client = registry.lock().unwrap().authenticate(&token)?;
+    let scope = &client.permissions;
+    // 引用的 ?vault= 要在保险库列表中查找，和命令一样先锁保险库列表再锁 AppState。
+    // 令牌只能访问当前保险库，并且只在范围内的条目和分组中查找，重名报错也不会泄露范围外的条目
+    if method == "resolve_reference" {
+        let ReferenceParams { reference } = params(raw)?;
+        let reference: SecretReference = reference.parse()?;
+        let vaults = app_handle.state::<Mutex<VaultRegistry>>();
+        let vaults = vaults.lock().unwrap();
+        let state = app_handle.state::<Mutex<AppState>>();
+        let app_state = state.lock().unwrap();
+        if !std::ptr::eq(vaults.state_for(&app_state, &reference)?, &*app_state) {
+            return Err(Error::PermissionDenied {
+                reason: "API tokens can only access the active vault".to_string(),
+            }
+            .into());
+        }
+        let vault = &app_state.vault;
+        let entries: Vec<_> = vault
+            .entries()?
+            .iter()
+            .filter(|e| scope.allows_group(e.group_id.as_deref()))
+            .cloned()
+            .collect();
+        let groups: Vec<_> = vault
+            .groups()?
+            .iter()
+            .filter(|g| scope.allows_group(Some(&g.id)))
+            .cloned()
+            .collect();
+        return to_value(resolve_in(
+            &app_state.vault_id,
+            &entries,
+            &groups,
+            &reference,
+        )?);
+    }
+
+    let state = app_handle.state::<Mutex<AppState>>();
+    let mut app_state = state.lock().unwrap();
+    let vault_id = app_state.vault_id.clone();
+    let vault = &mut app_state.vault;
+
+    // 不在范围内的条目当作不存在
+    let find_entry = |entries: &[PasswordEntry], id: &str| {
+        entries
+            .iter()
+            .find(|e| e.id == id && scope.allows_group(e.group_id.as_deref()))
+            .cloned()
+            .ok_or_else(|| Error::EntryNotFound { id: id.to_string() })
+    };
+
+    match method {
+        "get_status" => to_value(json!({
+            "version": API_VERSION,
+            "client": client.name,
+            "unlocked": vault.is_unlocked(),
+            "read_only": vault.is_read_only(),
+        })),
+        "get_all_entries" => {
+            let entries: Vec<_> = vault
+                .entries()?
+                .iter()
+                .filter(|e| scope.allows_group(e.group_id.as_deref()))
+                .collect();
+            to_value(entries)
+        }
+        "get_entry" => {
+            let IdParams { id } = params(raw)?;
+            to_value(find_entry(vault.entries()?, &id)?)
+        }
+        "add_entry" => {
+            scope.check_write()?;
+            let EntryParams { mut entry } = params(raw)?;
+            if !scope.allows_group(entry.group_id.as_deref()) {
+                return Err(Error::PermissionDenied {
+                    reason: "the entry is in a group outside the token's scope".to_string(),
+                }
+                .into());
+            }
+            // 新条目总是使用新的 id，客户端不能借用已有条目（可能在范围外）的 id；返回分配的 id
+            entry.id = uuid::Uuid::new_v4().to_string();
+            let id = entry.id.clone();
+            vault.add_entry(entry)?;
+            to_value(json!({ "id": id }))
+        }
+        "update_entry" => {
+            scope.check_write()?;
+            let EntryParams { entry } = params(raw)?;
+            find_entry(vault.entries()?, &entry.id)?;
+            if !scope.allows_group(entry.group_id.as_deref()) {
+                return Err(Error::PermissionDenied {
+                    reason: "cannot move the entry outside the token's scope".to_string(),
+                }
+                .into());
+            }
+            to_value(vault.update_entry(entry)?)
+        }
+        "delete_entry" => {
+            scope.check_write()?;
+            let IdParams { id } = params(raw)?;
+            find_entry(vault.entries()?, &id)?;
+            to_value(vault.delete_entry(&id)?)
+        }
+        "get_all_groups" => {
+            let groups: Vec<_> = vault
+                .groups()?
+                .iter()
+                .filter(|g| scope.allows_group(Some(&g.id)))
+                .collect();
+            to_value(groups)
+        }
+        "add_group" | "update_group" => {
+            scope.check_write()?;
+            scope.check_unrestricted()?;
+            let GroupParams { group } = params(raw)?;
+            if method == "add_group" {
+                to_value(vault.add_group(group)?)
+            } else {
+                to_value(vault.update_group(group)?)
+            }
+        }
+        "delete_group" => {
+            scope.check_write()?;
+            scope.check_unrestricted()?;
+            let IdParams { id } = params(raw)?;
+            to_value(vault.delete_group(&id)?)
+        }
+        "get_entry_reference" => {
+            let EntryReferenceParams { id, field } = params(raw)?;
+            let entry = find_entry(vault.entries()?, &id)?;
+            let field: ReferenceField = field.parse()?;
+            let reference =
+                SecretReference::for_entry(&entry, vault.groups()?, field, Some(&vault_id));
+            to_value(reference.to_string())
+        }
+        "get_totp" => {
+            let IdParams { id } = params(raw)?;
+            let entry = find_entry(vault.entries()?, &id)?;
+            let secret = entry
+                .totp_secret
+                .as_deref()
+                .filter(|s| !s.is_empty())
+                .ok_or_else(|| Error::invalid_input("totp", "the entry has no TOTP secret"))?;
+            let now_secs = (now_millis() / 1000) as u64;
+            to_value(json!({
+                "code": totp::generate(secret)?,
+                "expires_in": totp::PERIOD_SECS - now_secs % totp::PERIOD_SECS,
+            }))
+        }
+        "generate_totp" => {
+            let SecretParams { secret } = params(raw)?;
+            to_value(totp::generate(&secret)?)
+        }
+        "generate_totp_secret" => to_value(totp::generate_secret()),
+        "export_data" => {
+            scope.check_unrestricted()?;
+            to_value(vault.export()?)
+        }
+        _ => Err(not_found()),
+    }
+}
+
+// 申请中的分组可以写名称或 id，统一换成 id。需要保险库已解锁
+fn resolve_groups(
+    app_handle: &tauri::AppHandle,
+    mut permissions: ApiPermissions,
+) -> Result<ApiPermissions, Error> {
+    let Some(requested) = permissions.groups.take() else {
+        return Ok(permissions);
+    };
+    let state = app_handle.state::<Mutex<AppState>>();
+    let app_state = state.lock().unwrap();
+    let groups = app_state.vault.groups()?;
+    let ids = requested
+        .iter()
+        .map(|query| {
+            groups
+                .iter()
+                .find(|g| g.id == *query || g.name.eq_ignore_ascii_case(query))
+                .map(|g| g.id.clone())
+                .ok_or_else(|| Error::GroupNotFound { id: query.clone() })
+        })
+        .collect::<Result<_, _>>()?;
+    permissions.groups = Some(ids);
+    Ok(permissions)
+}
+
+// 处理一行请求，通知（没有 id）返回 None
+fn handle_line(app_handle: &tauri::AppHandle, connection: u64, line: &str) -> Option<RpcResponse> {
+    let (id, result) = match serde_json::from_str::<RpcRequest>(line) {
+        Err(e) => (
+            Value::Null,
+            Err(RpcError::new(-32700, format!("Parse error: {}", e))),
+        ),
+        Ok(request) if request.jsonrpc != "2.0" => (
+            request.id.unwrap_or(Value::Null),
+            Err(RpcError::new(
+                -32600,
+                "Invalid request: jsonrpc must be \"2.0\"",
+            )),
+        ),
+        Ok(request) => {
+            let result = dispatch(app_handle, connection, &request.method, &request.params);
+            (request.id?, result)
+        }
+    };
+    let (result, error) = match result {
+        Ok(value) => (Some(value), None),
+        Err(e) => (None, Some(e)),
+    };
+    Some(RpcResponse {
+        jsonrpc: "2.0",
+        id,
+        result,
+        error,
+    })
+}
+
+// 启动时在数据目录监听 api.sock，每个连接一个线程。另一个实例已在监听时不启动
+#[cfg(unix)]
+pub(crate) fn spawn_server(app_handle: tauri::AppHandle) {
+    use std::io::{BufRead, BufReader, Write};
+    use std::time::Duration;
+    use twopass_core::unix_socket::{bind_private, peer_is_current_user};
+
+    // 空闲连接最长保留时间
+    const CLIENT_TIMEOUT: Duration = Duration::from_secs(300);
+
+    let socket = app_handle
+        .state::<Mutex<ApiRegistry>>()
+        .lock()
+        .unwrap()
+        .socket
+        .clone();
+    let listener = match bind_private(&socket) {
+        Ok(Some(listener)) => listener,
+        Ok(None) => {
+            println!(
+                "⚠️ Another 2Pass instance is serving the API on {:?}",
+                socket
+            );
+            return;
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to start the API server: {}", e);
+            return;
+        }
+    };
+    println!("🔌 API listening on {:?}", socket);
+
+    std::thread::spawn(move || {
+        for (connection, stream) in (0u64..).zip(listener.incoming()) {
+            let Ok(stream) = stream else { continue };
+            if !peer_is_current_user(&stream).unwrap_or(false) {
+                eprintln!("⛔ Rejected API connection from another user");
+                continue;
+            }
+            let app_handle = app_handle.clone();
+            std::thread::spawn(move || {
+                let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+                let Ok(mut writer) = stream.try_clone() else {
+                    return;
+                };
+                for line in BufReader::new(stream).lines() {
+                    let Ok(line) = line else { break };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let Some(response) = handle_line(&app_handle, connection, &line) else {
+                        continue;
+                    };
+                    let mut out = serde_json::to_string(&response).unwrap();
+                    out.push('\n');
+                    if writer.write_all(out.as_bytes()).is_err() {
+                        break;
+                    }
+                }
+                app_handle
+                    .state::<Mutex<ApiRegistry>>()
+                    .lock()
+                    .unwrap()
+                    .drop_connection(connection);
+            });
+        }
+    });
+}
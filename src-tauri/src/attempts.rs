@@ -0,0 +1,28 @@
+// 登录界面显示的解锁失败状态。失败记录和退避规则在 twopass_core::attempts，桌面应用和命令行共用
+use crate::AppState;
+use serde::Serialize;
+use std::sync::Mutex;
+use twopass_core::attempts::LoginAttempts;
+
+/// 返回给登录界面的失败次数和限制状态
+#[derive(Debug, Clone, Serialize)]
+pub struct LoginStatus {
+    pub failed_attempts: u32,
+    pub first_failure_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+    pub retry_after_ms: u64, // 还需等待多久才能再次尝试，0 表示可以立即尝试
+    pub wipe_after_failures: u32,
+}
+
+#[tauri::command]
+pub fn get_login_status(state: tauri::State<Mutex<AppState>>) -> LoginStatus {
+    let app_state = state.lock().unwrap();
+    let attempts = LoginAttempts::load(&app_state.data_file);
+    LoginStatus {
+        failed_attempts: attempts.failed_attempts,
+        first_failure_at: attempts.first_failure_at,
+        last_failure_at: attempts.last_failure_at,
+        retry_after_ms: attempts.retry_after().as_millis() as u64,
+        wipe_after_failures: app_state.settings.wipe_after_failures,
+    }
+}
@@ -0,0 +1,27 @@
+// 数据目录的确定规则在 twopass_core::datadir，桌面应用和命令行共用；
+// 这里只提供系统的应用数据目录，并在启动时创建数据目录
+use crate::Error;
+use std::fs;
+use tauri::Manager;
+use twopass_core::datadir::DataDir;
+
+pub(crate) fn resolve(app_handle: &tauri::AppHandle) -> Result<DataDir, Error> {
+    let argument = DataDir::argument(std::env::args_os())?;
+    // 使用 Tauri 2.0 的 API 获取应用数据目录（跨平台兼容）
+    let data_dir = DataDir::resolve(argument, || {
+        app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| Error::io("Failed to get app data dir", e))
+    })?;
+
+    fs::create_dir_all(&data_dir.path)
+        .map_err(|e| Error::io("Failed to create data directory", e))?;
+    println!("📂 Data dir ({:?}): {:?}", data_dir.source, data_dir.path);
+    Ok(data_dir)
+}
+
+#[tauri::command]
+pub fn get_data_dir(data_dir: tauri::State<DataDir>) -> DataDir {
+    DataDir::clone(&data_dir)
+}
@@ -0,0 +1,180 @@
+// 紧急恢复包：生成恢复密钥并登记为 key slot，渲染可打印的 HTML / 纯文本恢复单
+use crate::{AppState, Error, SecretString};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use twopass_core::{format_timestamp, is_sqlite_path, KeySlotInfo};
+
+const EMERGENCY_KIT_LABEL: &str = "Emergency kit";
+
+// 恢复单里有恢复密钥，预先分配足够的缓冲区，避免扩容时在堆上留下未清零的副本
+const SHEET_CAPACITY: usize = 8 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct EmergencyKit {
+    pub slot: KeySlotInfo,
+    pub recovery_key: SecretString,
+    pub html: SecretString,
+    pub text: SecretString,
+}
+
+struct KitDetails<'a> {
+    vault_path: String,
+    restore_steps: Vec<String>,
+    vault_created: String,
+    generated: String,
+    recovery_key: &'a str,
+}
+
+#[tauri::command]
+pub fn generate_emergency_kit(state: tauri::State<Mutex<AppState>>) -> Result<EmergencyKit, Error> {
+    let mut app_state = state.lock().unwrap();
+    let recovery = app_state.vault.add_recovery_key(EMERGENCY_KIT_LABEL)?;
+
+    // 最早的 key slot 即保险库创建（或迁移到 key slot 格式）的时间
+    let vault_created_at = app_state
+        .vault
+        .key_slots()?
+        .iter()
+        .map(|s| s.created_at)
+        .min()
+        .unwrap_or(recovery.slot.created_at);
+
+    let details = KitDetails {
+        vault_path: app_state.data_file.display().to_string(),
+        restore_steps: restore_steps(&app_state.data_file, app_state.settings.backup_retention),
+        vault_created: format_timestamp(vault_created_at),
+        generated: format_timestamp(recovery.slot.created_at),
+        recovery_key: &recovery.recovery_key,
+    };
+    let text = render_text(&details);
+    let html = render_html(&details);
+
+    println!("🧰 Emergency kit generated");
+
+    Ok(EmergencyKit {
+        slot: recovery.slot,
+        recovery_key: recovery.recovery_key,
+        html,
+        text,
+    })
+}
+
+fn render_text(details: &KitDetails) -> SecretString {
+    let mut out = String::with_capacity(SHEET_CAPACITY);
+    let _ = write!(
+        out,
+        "2Pass Emergency Kit\n\
+         ===================\n\
+         \n\
+         Vault file:      {}\n\
+         Vault created:   {}\n\
+         Kit generated:   {}\n\
+         \n\
+         Recovery key:\n\
+         \n\
+         \x20   {}\n\
+         \n\
+         How to restore access\n\
+         ---------------------\n",
+        details.vault_path, details.vault_created, details.generated, details.recovery_key
+    );
+    for (i, step) in details.restore_steps.iter().enumerate() {
+        let _ = writeln!(out, "{}. {}", i + 1, step);
+    }
+    let _ = write!(out, "\n{}\n", WARNING);
+    SecretString::new(out)
+}
+
+fn render_html(details: &KitDetails) -> SecretString {
+    let mut out = String::with_capacity(SHEET_CAPACITY);
+    let _ = write!(
+        out,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>2Pass Emergency Kit</title>
+<style>
+body {{ font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; color: #111827; max-width: 720px; margin: 40px auto; padding: 0 24px; }}
+h1 {{ font-size: 1.75rem; margin-bottom: 4px; }}
+table {{ border-collapse: collapse; margin: 24px 0; }}
+th {{ text-align: left; padding: 6px 24px 6px 0; color: #4b5563; font-weight: 600; }}
+td {{ padding: 6px 0; word-break: break-all; }}
+.key {{ font-family: "SF Mono", Menlo, Consolas, monospace; font-size: 1.375rem; letter-spacing: 1px; padding: 20px; border: 2px dashed #9ca3af; border-radius: 12px; text-align: center; }}
+.warning {{ margin-top: 32px; padding: 12px 16px; background: #fef2f2; border: 1px solid #fca5a5; border-radius: 10px; color: #b91c1c; }}
+</style>
+</head>
+<body>
+<h1>🔐 2Pass Emergency Kit</h1>
+<p>Print this page and keep it somewhere safe, away from your computer.</p>
+<table>
+<tr><th>Vault file</th><td>{}</td></tr>
+<tr><th>Vault created</th><td>{}</td></tr>
+<tr><th>Kit generated</th><td>{}</td></tr>
+</table>
+<h2>Recovery key</h2>
+<div class="key">{}</div>
+<h2>How to restore access</h2>
+<ol>
+"#,
+        escape_html(&details.vault_path),
+        details.vault_created,
+        details.generated,
+        details.recovery_key
+    );
+    for step in &details.restore_steps {
+        let _ = writeln!(out, "<li>{}</li>", escape_html(step));
+    }
+    let _ = write!(
+        out,
+        "</ol>\n<p class=\"warning\">{}</p>\n</body>\n</html>\n",
+        escape_html(WARNING)
+    );
+    SecretString::new(out)
+}
+
+// 恢复文件的步骤取决于保险库的实际位置和存储后端：只有开启了备份的 JSON 文件才有 <文件名>.bak.* 自动备份
+fn restore_steps(vault_path: &Path, backup_retention: usize) -> Vec<String> {
+    let file_name = vault_path.file_name().unwrap_or_default().to_string_lossy();
+    let folder = vault_path
+        .parent()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    let restore_file = if is_sqlite_path(vault_path) {
+        format!(
+            "If the vault file is missing, copy your own copy of {file_name} back to {folder}. \
+             SQLite vaults are not backed up automatically, so keep a copy of this file somewhere safe."
+        )
+    } else if backup_retention == 0 {
+        format!(
+            "If the vault file is missing, copy your own copy of {file_name} back to {folder}. \
+             Automatic backups are turned off, so keep a copy of this file somewhere safe."
+        )
+    } else {
+        format!(
+            "If the vault file is missing, copy it back to {folder}, or rename its latest \
+             automatic backup there ({file_name}.bak.*) to {file_name}."
+        )
+    };
+    vec![
+        "Install 2Pass on your computer.".to_string(),
+        restore_file,
+        "If the vault is not in the 2Pass data folder, choose Open… on the unlock screen and select the vault file above.".to_string(),
+        "Start 2Pass and type the recovery key into the master password field on the unlock screen. Dashes, spaces and letter case are ignored.".to_string(),
+        "Open Settings → Change Master Password and choose a new master password. The current password is not required after unlocking with the recovery key.".to_string(),
+        "Generate a new emergency kit and destroy this one if you think it may have been seen by someone else.".to_string(),
+    ]
+}
+
+// 删除 key slot 只改写当前的保险库文件，之前的副本和 .bak.* 备份里仍然保留这个 slot
+const WARNING: &str = "Anyone who has this recovery key and your vault file can read all of your passwords. Removing the \"Emergency kit\" key slot revokes it only for the current vault file: older copies of the file, including automatic backups (.bak.*), still open with this key until they are deleted.";
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,135 @@
+// key slot 管理命令：具体逻辑在 twopass_core::keyslot
+use crate::{AppState, Error, SecretString};
+use std::sync::Mutex;
+use twopass_core::keyslot::{self, read_key_file};
+use twopass_core::{KeySlotInfo, KeySlotKind, RecoveryKeySlot, UnlockSecret, Vault};
+
+// 本次解锁使用的方式，前端据此决定是否允许不输入旧密码直接重设主密码
+#[tauri::command]
+pub fn get_unlock_method(state: tauri::State<Mutex<AppState>>) -> Option<KeySlotKind> {
+    state.lock().unwrap().vault.unlocked_with()
+}
+
+#[tauri::command]
+pub fn list_key_slots(state: tauri::State<Mutex<AppState>>) -> Result<Vec<KeySlotInfo>, Error> {
+    let app_state = state.lock().unwrap();
+    app_state.vault.key_slots()
+}
+
+#[tauri::command]
+pub fn add_password_slot(
+    password: SecretString,
+    label: String,
+    key_file_path: Option<String>,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<KeySlotInfo, Error> {
+    let mut app_state = state.lock().unwrap();
+    let key_file = key_file_path.as_deref().map(read_key_file).transpose()?;
+    app_state
+        .vault
+        .add_password_slot(&label, &password, key_file.as_deref().map(Vec::as_slice))
+}
+
+// 生成新的恢复密钥并登记为 key slot，返回的恢复密钥只会出现这一次
+#[tauri::command]
+pub fn add_recovery_key_slot(
+    label: String,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<RecoveryKeySlot, Error> {
+    let mut app_state = state.lock().unwrap();
+    app_state.vault.add_recovery_key(&label)
+}
+
+#[tauri::command]
+pub fn add_key_file_slot(
+    key_file_path: String,
+    label: String,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<KeySlotInfo, Error> {
+    let mut app_state = state.lock().unwrap();
+    let contents = read_key_file(&key_file_path)?;
+    app_state.vault.add_key_file_slot(&label, &contents)
+}
+
+#[tauri::command]
+pub fn remove_key_slot(id: String, state: tauri::State<Mutex<AppState>>) -> Result<(), Error> {
+    let mut app_state = state.lock().unwrap();
+    app_state.vault.remove_key_slot(&id)
+}
+
+#[tauri::command]
+pub fn unlock_with_key_file(
+    key_file_path: String,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<bool, Error> {
+    let contents = read_key_file(&key_file_path)?;
+    let mut app_state = state.lock().unwrap();
+    crate::unlock(&mut app_state, UnlockSecret::KeyFile(contents), false)
+}
+
+// 用恢复密钥或密钥文件解锁后（忘记主密码的情况），直接设置新的主密码，替换所有主密码 slot
+#[tauri::command]
+pub fn reset_master_password(
+    new_password: SecretString,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<(), Error> {
+    let mut app_state = state.lock().unwrap();
+    app_state.vault.reset_master_password(&new_password)
+}
+
+// 生成新的随机密钥文件，不覆盖已有文件
+#[tauri::command]
+pub fn generate_key_file(key_file_path: String) -> Result<(), Error> {
+    keyslot::generate_key_file(key_file_path)
+}
+
+// 让主密码 slot 额外要求密钥文件：之后解锁需要同时提供密码和密钥文件
+#[tauri::command]
+pub fn bind_key_file(
+    master_password: SecretString,
+    key_file_path: String,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<KeySlotInfo, Error> {
+    let key_file = read_key_file(&key_file_path)?;
+    let mut app_state = state.lock().unwrap();
+    check_password(&mut app_state, |vault| {
+        vault.bind_key_file(master_password, &key_file)
+    })
+}
+
+// 解除主密码 slot 与密钥文件的绑定，需要同时提供密码和当前的密钥文件
+#[tauri::command]
+pub fn unbind_key_file(
+    master_password: SecretString,
+    key_file_path: String,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<KeySlotInfo, Error> {
+    let key_file = read_key_file(&key_file_path)?;
+    let mut app_state = state.lock().unwrap();
+    check_password(&mut app_state, |vault| {
+        vault.unbind_key_file(master_password, key_file)
+    })
+}
+
+// 核对主密码的操作和解锁一样受失败次数限制，密码错误计为一次失败，不能借此绕过退避猜测主密码
+fn check_password(
+    app_state: &mut AppState,
+    operation: impl FnOnce(&mut Vault) -> Result<KeySlotInfo, Error>,
+) -> Result<KeySlotInfo, Error> {
+    let mut info = None;
+    let matched = twopass_core::attempts::throttled(
+        &mut app_state.vault,
+        &app_state.data_file,
+        app_state.settings.wipe_after_failures,
+        false,
+        |vault| match operation(vault) {
+            Ok(slot) => {
+                info = Some(slot);
+                Ok(true)
+            }
+            Err(Error::WrongPassword) => Ok(false),
+            Err(e) => Err(e),
+        },
+    )?;
+    info.filter(|_| matched).ok_or(Error::WrongPassword)
+}
@@ -0,0 +1,105 @@
+// 密钥引用命令：解析 2pass:// 引用，为条目生成可以复制的规范引用，以及预览模板用到的条目。
+// URI 格式见 twopass_core::reference
+use crate::vaults::VaultRegistry;
+use crate::{AppState, Error, PasswordEntry, PasswordGroup, SecretString};
+use serde::Serialize;
+use std::sync::Mutex;
+use twopass_core::reference::ReferenceField;
+use twopass_core::template::{self, TemplateReference};
+use twopass_core::SecretReference;
+
+#[derive(Debug, Serialize)]
+pub struct ResolvedReference {
+    pub vault_id: String,
+    pub entry_id: String,
+    pub entry_title: String,
+    pub value: SecretString,
+}
+
+pub(crate) fn resolve_in(
+    vault_id: &str,
+    entries: &[PasswordEntry],
+    groups: &[PasswordGroup],
+    reference: &SecretReference,
+) -> Result<ResolvedReference, Error> {
+    let entry = reference.find_entry(entries, groups)?;
+    Ok(ResolvedReference {
+        vault_id: vault_id.to_string(),
+        entry_id: entry.id.clone(),
+        entry_title: entry.title.clone(),
+        value: reference.resolve(entries, groups)?,
+    })
+}
+
+// 引用可以指向其它已解锁的保险库；同名的条目或分组返回 AmbiguousReference
+#[tauri::command]
+pub fn resolve_reference(
+    reference: String,
+    registry: tauri::State<Mutex<VaultRegistry>>,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<ResolvedReference, Error> {
+    let reference: SecretReference = reference.parse()?;
+    let registry = registry.lock().unwrap();
+    let app_state = state.lock().unwrap();
+    let target = registry.state_for(&app_state, &reference)?;
+    let vault = &target.vault;
+    resolve_in(
+        &target.vault_id,
+        vault.entries()?,
+        vault.groups()?,
+        &reference,
+    )
+}
+
+// 条目字段的规范引用，按 id 指向条目，条目改名或移动分组后仍然有效。供界面的"复制引用"使用
+#[tauri::command]
+pub fn get_entry_reference(
+    id: String,
+    field: String,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<String, Error> {
+    let field: ReferenceField = field.parse()?;
+    let app_state = state.lock().unwrap();
+    let vault = &app_state.vault;
+    let entry = vault
+        .entries()?
+        .iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| Error::EntryNotFound { id: id.clone() })?;
+    let reference =
+        SecretReference::for_entry(entry, vault.groups()?, field, Some(&app_state.vault_id));
+    Ok(reference.to_string())
+}
+
+// 列出模板中 2pass:// 引用对应的条目，不返回字段的值。写了 ?vault= 的引用在对应的保险库中查找
+#[tauri::command]
+pub fn preview_template(
+    template: String,
+    registry: tauri::State<Mutex<VaultRegistry>>,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<Vec<TemplateReference>, Error> {
+    let registry = registry.lock().unwrap();
+    let app_state = state.lock().unwrap();
+    let vault = &app_state.vault;
+    let mut usages = template::preview(&template, vault.entries()?, vault.groups()?);
+    for usage in &mut usages {
+        let Ok(reference) = usage.reference.parse::<SecretReference>() else {
+            continue;
+        };
+        if reference.vault.is_none() {
+            continue;
+        }
+        let found = registry
+            .state_for(&app_state, &reference)
+            .and_then(|state| {
+                let vault = &state.vault;
+                reference
+                    .find_entry(vault.entries()?, vault.groups()?)
+                    .cloned()
+            });
+        usage.entry_id = found.as_ref().ok().map(|e| e.id.clone());
+        usage.entry_title = found.as_ref().ok().map(|e| e.title.clone());
+        usage.error = found.err();
+    }
+    Ok(usages)
+}
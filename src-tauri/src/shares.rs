@@ -0,0 +1,38 @@
+// 恢复分片命令：拆分和合并在 twopass_core::shares
+use crate::{AppState, Error, SecretString};
+use std::sync::Mutex;
+use twopass_core::shares::{self, RecoveryShares};
+use twopass_core::UnlockSecret;
+
+#[tauri::command]
+pub fn create_recovery_shares(
+    threshold: u8,
+    share_count: u8,
+    label: String,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<RecoveryShares, Error> {
+    let mut app_state = state.lock().unwrap();
+    app_state
+        .vault
+        .create_recovery_shares(threshold, share_count, &label)
+}
+
+// 用任意 K 份分片解锁，并设置新的主密码
+#[tauri::command]
+pub fn recover_with_shares(
+    shares: Vec<SecretString>,
+    new_password: SecretString,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<bool, Error> {
+    let secret = shares::combine_shares(&shares)?;
+
+    let mut app_state = state.lock().unwrap();
+    if !crate::unlock(&mut app_state, UnlockSecret::Shares(secret), false)? {
+        return Ok(false);
+    }
+    app_state.vault.replace_password_slots(&new_password)?;
+
+    println!("🧩 Vault recovered with shares, master password reset");
+
+    Ok(true)
+}
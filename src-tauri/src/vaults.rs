@@ -0,0 +1,307 @@
+// 多保险库：保险库列表（名称 + 路径）保存在应用数据目录的 vaults.json，格式在 twopass_core::datadir 中定义。
+// 当前保险库的状态就是受管理的 Mutex<AppState>，其它保险库的状态留在列表里，切换时互换
+use crate::{AppSettings, AppState, Error, SecretString};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use twopass_core::datadir::{VaultEntry, VaultList, DEFAULT_VAULT_NAME};
+use twopass_core::keyslot::read_key_file;
+use twopass_core::{open_storage, SecretReference};
+
+/// 返回给前端的保险库信息
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultInfo {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub active: bool,
+    pub unlocked: bool,
+    pub read_only: bool,
+    pub exists: bool, // 保险库文件是否存在（尚未创建主密码或文件已被移走时为 false）
+}
+
+pub struct VaultRegistry {
+    data_dir: PathBuf,
+    settings_file: PathBuf, // 设置对所有保险库通用
+    active: String,
+    vaults: Vec<VaultEntry>,
+    inactive: HashMap<String, AppState>,
+}
+
+impl VaultRegistry {
+    // 首次启动时把 data_dir/data.json 登记为默认保险库，兼容只有一个保险库的旧版本
+    pub(crate) fn load(data_dir: &Path) -> Self {
+        let mut list = VaultList::load(data_dir);
+        list.ensure_default(data_dir);
+
+        let registry = Self {
+            data_dir: data_dir.to_path_buf(),
+            settings_file: AppSettings::file(data_dir),
+            active: list.active,
+            vaults: list.vaults,
+            inactive: HashMap::new(),
+        };
+        if let Err(e) = registry.save() {
+            eprintln!("❌ Failed to save vault list: {}", e);
+        }
+        registry
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        VaultList {
+            active: self.active.clone(),
+            vaults: self.vaults.clone(),
+        }
+        .save(&self.data_dir)
+    }
+
+    // 启动时当前保险库的状态
+    pub(crate) fn active_state(&self) -> AppState {
+        let vault = self
+            .vaults
+            .iter()
+            .find(|v| v.id == self.active)
+            .expect("active vault is always registered");
+        AppState::new(vault, self.settings_file.clone())
+    }
+
+    /// 引用的 ?vault= 指向的保险库状态：当前保险库，或切换走之后仍保留在列表中的保险库
+    pub(crate) fn state_for<'a>(
+        &'a self,
+        app_state: &'a AppState,
+        reference: &SecretReference,
+    ) -> Result<&'a AppState, Error> {
+        let vault = self
+            .vaults
+            .iter()
+            .filter(|v| reference.matches_vault(&v.id, &v.name))
+            .min_by_key(|v| v.id != self.active) // 没有写 vault 时取当前保险库
+            .ok_or_else(|| Error::VaultNotFound {
+                id: reference.vault.clone().unwrap_or_default(),
+            })?;
+        if vault.id == self.active {
+            return Ok(app_state);
+        }
+        // 本次运行中没有打开过的保险库一定是锁定的
+        self.inactive.get(&vault.id).ok_or(Error::NotAuthenticated)
+    }
+
+    fn find(&self, id: &str) -> Result<&VaultEntry, Error> {
+        self.vaults
+            .iter()
+            .find(|v| v.id == id)
+            .ok_or_else(|| Error::VaultNotFound { id: id.to_string() })
+    }
+
+    fn find_by_path(&self, path: &Path) -> Option<&VaultEntry> {
+        let path = canonical(path);
+        self.vaults.iter().find(|v| canonical(&v.path) == path)
+    }
+
+    fn info(&self, vault: &VaultEntry, app_state: &AppState) -> VaultInfo {
+        let active = vault.id == self.active;
+        let state = if active {
+            Some(app_state)
+        } else {
+            self.inactive.get(&vault.id)
+        };
+        let unlocked = state.is_some_and(|s| s.vault.is_unlocked());
+        VaultInfo {
+            id: vault.id.clone(),
+            name: vault.name.clone(),
+            path: vault.path.display().to_string(),
+            active,
+            unlocked,
+            read_only: unlocked && state.is_some_and(|s| s.vault.is_read_only()),
+            exists: vault.path.exists(),
+        }
+    }
+
+    // 把 next 换成当前保险库，原来的状态保留在列表中
+    fn activate(&mut self, app_state: &mut AppState, mut next: AppState) -> Result<(), Error> {
+        // 设置可能在其它保险库打开时被修改过
+        next.settings = AppSettings::load(&self.settings_file);
+        next.vault
+            .set_backup_retention(next.settings.backup_retention)?;
+        next.touch();
+        self.active = next.vault_id.clone();
+        let previous = std::mem::replace(app_state, next);
+        self.inactive.insert(previous.vault_id.clone(), previous);
+        self.save()
+    }
+
+    fn switch(&mut self, app_state: &mut AppState, id: &str) -> Result<(), Error> {
+        if id == self.active {
+            return Ok(());
+        }
+        let next = match self.inactive.remove(id) {
+            Some(state) => state,
+            None => AppState::new(self.find(id)?, self.settings_file.clone()),
+        };
+        self.activate(app_state, next)
+    }
+
+    fn register(&mut self, name: Option<String>, path: &Path) -> VaultEntry {
+        let vault = VaultEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: vault_name(name, path),
+            path: path.to_path_buf(),
+        };
+        self.vaults.push(vault.clone());
+        vault
+    }
+
+    // 后台空闲检查：锁定超时的非当前保险库
+    pub(crate) fn lock_idle(&mut self) {
+        for state in self.inactive.values_mut() {
+            if state.idle_timed_out() {
+                state.lock();
+            }
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// 未指定名称时使用文件名（不含扩展名）
+fn vault_name(name: Option<String>, path: &Path) -> String {
+    name.map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| DEFAULT_VAULT_NAME.to_string())
+}
+
+fn require_absolute(path: &str) -> Result<PathBuf, Error> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() {
+        return Err(Error::invalid_input("path", "must be absolute"));
+    }
+    Ok(path)
+}
+
+#[tauri::command]
+pub fn list_vaults(
+    registry: tauri::State<Mutex<VaultRegistry>>,
+    state: tauri::State<Mutex<AppState>>,
+) -> Vec<VaultInfo> {
+    let registry = registry.lock().unwrap();
+    let app_state = state.lock().unwrap();
+    registry
+        .vaults
+        .iter()
+        .map(|v| registry.info(v, &app_state))
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_active_vault(
+    registry: tauri::State<Mutex<VaultRegistry>>,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<VaultInfo, Error> {
+    let registry = registry.lock().unwrap();
+    let app_state = state.lock().unwrap();
+    let vault = registry.find(&registry.active)?;
+    Ok(registry.info(vault, &app_state))
+}
+
+// 在新路径创建保险库并切换过去（创建后处于解锁状态）
+#[tauri::command]
+pub fn create_vault(
+    name: Option<String>,
+    path: String,
+    master_password: SecretString,
+    key_file_path: Option<String>,
+    registry: tauri::State<Mutex<VaultRegistry>>,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<VaultInfo, Error> {
+    let path = require_absolute(&path)?;
+    if path.exists() {
+        return Err(Error::FileExists {
+            path: path.display().to_string(),
+        });
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::io("Failed to create vault directory", e))?;
+    }
+
+    let mut registry = registry.lock().unwrap();
+    let mut app_state = state.lock().unwrap();
+    let vault = VaultEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: vault_name(name, &path),
+        path,
+    };
+    let key_file = key_file_path.as_deref().map(read_key_file).transpose()?;
+    let mut next = AppState::new(&vault, registry.settings_file.clone());
+    next.vault
+        .create(&master_password, key_file.as_deref().map(Vec::as_slice))?;
+
+    registry.vaults.push(vault.clone());
+    registry.activate(&mut app_state, next)?;
+    println!("🗄️ Created vault {:?} at {:?}", vault.name, vault.path);
+
+    Ok(registry.info(&vault, &app_state))
+}
+
+// 登记已有的保险库文件并切换过去；已登记过的路径直接切换
+#[tauri::command]
+pub fn open_existing_vault(
+    name: Option<String>,
+    path: String,
+    registry: tauri::State<Mutex<VaultRegistry>>,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<VaultInfo, Error> {
+    let path = require_absolute(&path)?;
+    open_storage(&path, 0)
+        .read()
+        .map_err(|e| Error::NotAVault {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+
+    let mut registry = registry.lock().unwrap();
+    let mut app_state = state.lock().unwrap();
+    let vault = match registry.find_by_path(&path) {
+        Some(vault) => vault.clone(),
+        None => registry.register(name, &path),
+    };
+    registry.switch(&mut app_state, &vault.id)?;
+    println!("🗄️ Opened vault {:?} at {:?}", vault.name, vault.path);
+
+    Ok(registry.info(&vault, &app_state))
+}
+
+#[tauri::command]
+pub fn switch_vault(
+    id: String,
+    registry: tauri::State<Mutex<VaultRegistry>>,
+    state: tauri::State<Mutex<AppState>>,
+) -> Result<VaultInfo, Error> {
+    let mut registry = registry.lock().unwrap();
+    let mut app_state = state.lock().unwrap();
+    registry.switch(&mut app_state, &id)?;
+    let vault = registry.find(&id)?;
+    println!("🗄️ Switched to vault {:?}", vault.name);
+
+    Ok(registry.info(vault, &app_state))
+}
+
+// 从列表中移除保险库，不删除保险库文件
+#[tauri::command]
+pub fn forget_vault(id: String, registry: tauri::State<Mutex<VaultRegistry>>) -> Result<(), Error> {
+    let mut registry = registry.lock().unwrap();
+    if id == registry.active {
+        return Err(Error::ActiveVault);
+    }
+    registry.find(&id)?;
+    registry.vaults.retain(|v| v.id != id);
+    registry.inactive.remove(&id);
+    registry.save()
+}
@@ -0,0 +1,50 @@
+// 检测保险库文件被其它程序（另一个实例、同步工具）修改：
+// 记录最近一次读写后文件的修改时间和大小，后台定期比较，发生变化时通知前端重新加载；
+// 保存前同样检查，避免覆盖别人的修改
+use crate::{AppState, Error};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use twopass_core::FileStamp;
+
+const CHANGE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+// 后台线程：当前保险库的文件发生外部修改时发出 vault-changed 事件，同一次修改只通知一次
+pub(crate) fn spawn_change_watcher(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut notified = None;
+        loop {
+            std::thread::sleep(CHANGE_CHECK_INTERVAL);
+
+            let state = app_handle.state::<Mutex<AppState>>();
+            let app_state = state.lock().unwrap();
+            if !app_state.vault.is_unlocked() || !app_state.vault.changed_externally() {
+                continue;
+            }
+            let current = (
+                app_state.vault_id.clone(),
+                FileStamp::of(&app_state.data_file),
+            );
+            if notified.as_ref() == Some(&current) {
+                continue;
+            }
+            drop(app_state);
+
+            println!("🔄 Vault file changed externally");
+            if let Err(e) = app_handle.emit("vault-changed", &current.0) {
+                eprintln!("❌ Failed to emit vault-changed event: {}", e);
+            }
+            notified = Some(current);
+        }
+    });
+}
+
+// 用当前的 vault key 重新读取保险库文件；文件换成了别的保险库时锁定，需要重新解锁
+#[tauri::command]
+pub fn reload_vault(state: tauri::State<Mutex<AppState>>) -> Result<(), Error> {
+    let mut app_state = state.lock().unwrap();
+    app_state.vault.reload()?;
+    println!("🔄 Vault reloaded from disk");
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { useTranslation } from "react-i18next";
 import Login from "./components/Login";
 import GroupList from "./components/GroupList";
@@ -10,10 +11,12 @@ import GeneratorView from "./components/GeneratorView";
 import Settings from "./components/Settings";
 import About from "./components/About";
 import ToastContainer from "./components/ToastContainer";
-import { PasswordEntry, PasswordGroup } from "./types";
+import ApiRequestDialog from "./components/ApiRequestDialog";
+import { ApiTokenRequest, PasswordEntry, PasswordGroup, VaultInfo } from "./types";
 import { useKeyboard } from "./hooks/useKeyboard";
 import { useToast } from "./hooks/useToast";
 import { useResponsive } from "./hooks/useResponsive";
+import { errorMessage } from "./utils/errors";
 import "./App.css";
 import "./styles/responsive/index.css";
 
@@ -32,6 +35,8 @@ function App() {
   const [searchTerm, setSearchTerm] = useState("");
   const [autoLockTimeout, setAutoLockTimeout] = useState<number>(0); // 0 表示禁用，单位：分钟
   const [theme, setTheme] = useState<string>("default");
+  const [readOnly, setReadOnly] = useState(false);
+  const [apiRequests, setApiRequests] = useState<ApiTokenRequest[]>([]);
   const toast = useToast();
 
   useEffect(() => {
@@ -58,9 +63,7 @@ function App() {
 
       timeoutId = setTimeout(() => {
         console.log(t("common.autoLockTriggered"));
-        setIsAuthenticated(false);
-        setEntries([]);
-        setCurrentView("passwords");
+        handleLock();
       }, autoLockTimeout * 60 * 1000); // 转换为毫秒
     };
 
@@ -81,6 +84,69 @@ function App() {
     };
   }, [isAuthenticated, autoLockTimeout]);
 
+  // 后端空闲超时锁定后同步界面状态
+  useEffect(() => {
+    const unlisten = listen("vault-locked", () => {
+      console.log(t("common.autoLockTriggered"));
+      clearSession();
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 保险库文件被其它程序修改后重新加载，避免之后的保存覆盖别人的修改
+  useEffect(() => {
+    const unlisten = listen("vault-changed", async () => {
+      try {
+        await invoke("reload_vault");
+        await loadEntries();
+        await loadGroups();
+        toast.info(t("common.vaultReloaded"));
+      } catch (error) {
+        toast.error(t("common.vaultReloadFailed") + "：" + errorMessage(t, error));
+        // 文件被换成了别的保险库时后端已锁定
+        const vault = await invoke<VaultInfo>("get_active_vault");
+        if (!vault.unlocked) {
+          clearSession();
+        }
+      }
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 本机 API 的令牌申请，逐个请用户批准
+  useEffect(() => {
+    invoke<ApiTokenRequest[]>("get_pending_api_requests")
+      .then(setApiRequests)
+      .catch((err) => console.error("Failed to load API requests:", err));
+    const unlisten = listen<ApiTokenRequest>("api-token-requested", (event) => {
+      setApiRequests((requests) => [...requests, event.payload]);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  const handleApiRequest = async (request: ApiTokenRequest, approve: boolean) => {
+    try {
+      await invoke(approve ? "approve_api_request" : "deny_api_request", { id: request.id });
+      if (approve) {
+        toast.success(t("api.approved", { name: request.name }));
+      } else {
+        toast.info(t("api.denied", { name: request.name }));
+      }
+    } catch (error) {
+      toast.error(errorMessage(t, error));
+    }
+    setApiRequests((requests) => requests.filter((r) => r.id !== request.id));
+  };
+
   const loadEntries = async () => {
     try {
       const data = await invoke<PasswordEntry[]>("get_all_entries");
@@ -114,11 +180,15 @@ function App() {
     // 开始加载数据
     try {
       await Promise.all([loadEntries(), loadGroups()]);
+      const vault = await invoke<VaultInfo>("get_active_vault");
+      setReadOnly(vault.read_only);
 
       // 加载自动锁定设置
       const savedTimeout = localStorage.getItem("autoLockTimeout");
       if (savedTimeout) {
-        setAutoLockTimeout(parseInt(savedTimeout, 10));
+        const minutes = parseInt(savedTimeout, 10);
+        setAutoLockTimeout(minutes);
+        await syncAutoLockSetting(minutes);
       }
 
       // 数据加载完成后才设置为已认证
@@ -192,7 +262,7 @@ function App() {
       toast.success(editingEntry ? t("passwords.passwordUpdated") : t("passwords.passwordAdded"));
     } catch (error) {
       console.error("Failed to save entry:", error);
-      toast.error(t("passwords.saveFailed") + "：" + error);
+      toast.error(t("passwords.saveFailed") + "：" + errorMessage(t, error));
     }
   };
 
@@ -203,7 +273,7 @@ function App() {
       toast.success(t("passwords.passwordDeleted"));
     } catch (error) {
       console.error("Failed to delete entry:", error);
-      toast.error(t("passwords.deleteFailed") + "：" + error);
+      toast.error(t("passwords.deleteFailed") + "：" + errorMessage(t, error));
     }
   };
 
@@ -222,7 +292,7 @@ function App() {
       console.log("数据重新加载完成");
     } catch (error) {
       console.error("Failed to update order:", error);
-      toast.error(t("passwords.updateOrderFailed") + "：" + error);
+      toast.error(t("passwords.updateOrderFailed") + "：" + errorMessage(t, error));
     }
   };
 
@@ -237,13 +307,23 @@ function App() {
     setEditingEntry(undefined);
   };
 
-  const handleLock = () => {
+  const clearSession = () => {
     setIsAuthenticated(false);
     setEntries([]);
     setGroups([]);
     setCurrentView("passwords");
   };
 
+  const handleLock = async () => {
+    // 让后端清除密钥和解密后的数据
+    try {
+      await invoke("lock_vault");
+    } catch (error) {
+      console.error("Failed to lock vault:", error);
+    }
+    clearSession();
+  };
+
   const handleAddGroup = () => {
     setEditingGroup(undefined);
     setShowGroupForm(true);
@@ -274,7 +354,7 @@ function App() {
       toast.success(editingGroup ? t("groups.groupUpdated") : t("groups.groupAdded"));
     } catch (error) {
       console.error("Failed to save group:", error);
-      toast.error(t("groups.saveGroupFailed") + "：" + error);
+      toast.error(t("groups.saveGroupFailed") + "：" + errorMessage(t, error));
     }
   };
 
@@ -288,7 +368,7 @@ function App() {
       toast.success(t("groups.groupDeleted"));
     } catch (error) {
       console.error("Failed to delete group:", error);
-      toast.error(t("groups.deleteGroupFailed") + "：" + error);
+      toast.error(t("groups.deleteGroupFailed") + "：" + errorMessage(t, error));
     }
   };
 
@@ -303,15 +383,26 @@ function App() {
       }
     } catch (error) {
       console.error("Failed to update group order:", error);
-      toast.error(t("groups.updateGroupOrderFailed") + "：" + error);
+      toast.error(t("groups.updateGroupOrderFailed") + "：" + errorMessage(t, error));
       // 失败时重新加载
       await loadGroups();
     }
   };
 
+  // 同步到后端，由后端的空闲计时器负责清除内存中的密钥
+  const syncAutoLockSetting = async (minutes: number) => {
+    try {
+      const settings = await invoke<Record<string, unknown>>("get_settings");
+      await invoke("update_settings", { settings: { ...settings, auto_lock_minutes: minutes } });
+    } catch (error) {
+      console.error("Failed to sync auto lock setting:", error);
+    }
+  };
+
   const handleAutoLockChange = (minutes: number) => {
     setAutoLockTimeout(minutes);
     localStorage.setItem("autoLockTimeout", minutes.toString());
+    syncAutoLockSetting(minutes);
   };
 
   const handleRefresh = async () => {
@@ -367,7 +458,7 @@ function App() {
       toast.success(t("passwords.movedToGroup", { groupName }));
     } catch (error) {
       console.error("Failed to move entry:", error);
-      toast.error(t("passwords.moveFailed") + "：" + error);
+      toast.error(t("passwords.moveFailed") + "：" + errorMessage(t, error));
     }
   };
 
@@ -484,6 +575,11 @@ function App() {
           </button>
         </nav>
         <div className="header-right">
+          {readOnly && (
+            <span className="read-only-badge" title={t("nav.readOnlyDescription")}>
+              👁️ {t("nav.readOnly")}
+            </span>
+          )}
           <button onClick={handleLock} className="lock-btn" title={t("nav.lock")}>
             🔒 {t("nav.lock")}
           </button>
@@ -516,6 +612,15 @@ function App() {
         />
       )}
 
+      {apiRequests.length > 0 && (
+        <ApiRequestDialog
+          request={apiRequests[0]}
+          groups={groups}
+          onApprove={() => handleApiRequest(apiRequests[0], true)}
+          onDeny={() => handleApiRequest(apiRequests[0], false)}
+        />
+      )}
+
       {/* Toast 通知 */}
       <ToastContainer toasts={toast.toasts} onClose={toast.removeToast} />
     </div>
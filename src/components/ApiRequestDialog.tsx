@@ -0,0 +1,40 @@
+import { useTranslation } from "react-i18next";
+import { ApiTokenRequest, PasswordGroup } from "../types";
+import { describePermissions } from "../utils/api";
+import "../styles/ConfirmDialog.css";
+
+interface ApiRequestDialogProps {
+  request: ApiTokenRequest;
+  groups: PasswordGroup[];
+  onApprove: () => void;
+  onDeny: () => void;
+}
+
+// 本机 API 的令牌申请：批准后程序才能通过 api.sock 读取保险库。点击遮罩不关闭，避免误拒绝
+function ApiRequestDialog({ request, groups, onApprove, onDeny }: ApiRequestDialogProps) {
+  const { t } = useTranslation();
+
+  return (
+    <div className="confirm-overlay">
+      <div className="confirm-dialog">
+        <div className="confirm-icon warning">🔌</div>
+        <h3 className="confirm-title">{t("api.requestTitle")}</h3>
+        <p className="confirm-message">
+          {t("api.requestMessage", { name: request.name })}
+          <br />
+          <strong>{describePermissions(t, request.permissions, groups)}</strong>
+        </p>
+        <div className="confirm-actions">
+          <button onClick={onDeny} className="btn-cancel">
+            {t("api.deny")}
+          </button>
+          <button onClick={onApprove} className="btn-confirm warning">
+            {t("api.approve")}
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
+
+export default ApiRequestDialog;
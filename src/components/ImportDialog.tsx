@@ -1,6 +1,7 @@
 import { useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useTranslation } from "react-i18next";
+import { errorMessage } from "../utils/errors";
 import "../styles/ImportDialog.css";
 
 interface ImportDialogProps {
@@ -78,7 +79,7 @@ function ImportDialog({ onClose, onSuccess }: ImportDialogProps) {
         onClose();
       }, 2000);
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(t, err));
       setImportProgress(0);
     } finally {
       setIsImporting(false);
@@ -1,33 +1,126 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useTranslation } from "react-i18next";
-import { LoginProps } from "../types";
+import { LoginProps, VaultInfo } from "../types";
+import { errorMessage } from "../utils/errors";
 import "../styles/Login.css";
 
+interface LoginStatus {
+  failed_attempts: number;
+  last_failure_at: number | null;
+  retry_after_ms: number;
+  wipe_after_failures: number;
+}
+
 function Login({ onLogin }: LoginProps) {
   const { t } = useTranslation();
   const [masterPassword, setMasterPassword] = useState("");
   const [confirmPassword, setConfirmPassword] = useState("");
+  const [keyFilePath, setKeyFilePath] = useState<string | null>(null);
+  const [readOnly, setReadOnly] = useState(false);
   const [isCreating, setIsCreating] = useState(false);
   const [error, setError] = useState("");
   const [loading, setLoading] = useState(false);
+  const [loginStatus, setLoginStatus] = useState<LoginStatus | null>(null);
+  const [retrySeconds, setRetrySeconds] = useState(0);
+  const [vaults, setVaults] = useState<VaultInfo[]>([]);
+  const [newVaultPath, setNewVaultPath] = useState<string | null>(null);
+  // 当前保险库尚未创建，或正在新建另一个保险库
+  const creating = isCreating || newVaultPath !== null;
 
   useEffect(() => {
     checkMasterPassword();
+    refreshLoginStatus();
+    loadVaults();
   }, []);
 
+  // 退避期间倒计时，结束前禁用解锁按钮
+  useEffect(() => {
+    if (retrySeconds <= 0) return;
+    const timer = setTimeout(() => setRetrySeconds((s) => s - 1), 1000);
+    return () => clearTimeout(timer);
+  }, [retrySeconds]);
+
   const checkMasterPassword = async () => {
     const exists = await invoke<boolean>("check_master_password_exists");
     setIsCreating(!exists);
   };
 
+  const loadVaults = async () => {
+    setVaults(await invoke<VaultInfo[]>("list_vaults"));
+  };
+
+  // 切换保险库后重新读取它的状态；仍处于解锁状态的保险库直接进入
+  const afterVaultChange = async (vault: VaultInfo) => {
+    setError("");
+    setMasterPassword("");
+    setConfirmPassword("");
+    setKeyFilePath(null);
+    setNewVaultPath(null);
+    await loadVaults();
+    await checkMasterPassword();
+    await refreshLoginStatus();
+    if (vault.unlocked) {
+      await onLogin();
+    }
+  };
+
+  const handleSwitchVault = async (id: string) => {
+    try {
+      await afterVaultChange(await invoke<VaultInfo>("switch_vault", { id }));
+    } catch (err) {
+      setError(t("login.vaultSwitchFailed") + ": " + errorMessage(t, err));
+    }
+  };
+
+  const handleOpenVault = async () => {
+    const { open } = await import("@tauri-apps/plugin-dialog");
+    const selected = await open({
+      multiple: false,
+      directory: false,
+      filters: [{ name: "2Pass Vault", extensions: ["json", "db"] }],
+    });
+    if (typeof selected !== "string") return;
+    try {
+      await afterVaultChange(await invoke<VaultInfo>("open_existing_vault", { name: null, path: selected }));
+    } catch (err) {
+      setError(t("login.vaultOpenFailed") + ": " + errorMessage(t, err));
+    }
+  };
+
+  // 先选择新保险库的保存位置，再在下方表单中设置主密码
+  const handleNewVault = async () => {
+    const { save } = await import("@tauri-apps/plugin-dialog");
+    const selected = await save({
+      defaultPath: "vault.json",
+      filters: [{ name: "2Pass Vault", extensions: ["json", "db"] }],
+    });
+    if (!selected) return;
+    setError("");
+    setNewVaultPath(selected);
+  };
+
+  const refreshLoginStatus = async () => {
+    const status = await invoke<LoginStatus>("get_login_status");
+    setLoginStatus(status);
+    setRetrySeconds(Math.ceil(status.retry_after_ms / 1000));
+  };
+
+  const handleSelectKeyFile = async () => {
+    const { open } = await import("@tauri-apps/plugin-dialog");
+    const selected = await open({ multiple: false, directory: false });
+    if (typeof selected === "string") {
+      setKeyFilePath(selected);
+    }
+  };
+
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
     setError("");
     setLoading(true);
 
     try {
-      if (isCreating) {
+      if (creating) {
         if (masterPassword !== confirmPassword) {
           setError(t("changeMasterPassword.passwordMismatch"));
           setLoading(false);
@@ -38,12 +131,18 @@ function Login({ onLogin }: LoginProps) {
           setLoading(false);
           return;
         }
-        await invoke("create_master_password", { masterPassword });
+        if (newVaultPath) {
+          await invoke("create_vault", { name: null, path: newVaultPath, masterPassword, keyFilePath });
+        } else {
+          await invoke("create_master_password", { masterPassword, keyFilePath });
+        }
         // 等待数据加载完成
         await onLogin();
       } else {
         const valid = await invoke<boolean>("verify_master_password", {
           masterPassword,
+          keyFilePath,
+          readOnly,
         });
         if (valid) {
           // 等待数据加载完成
@@ -51,11 +150,15 @@ function Login({ onLogin }: LoginProps) {
         } else {
           setError(t("login.wrongPassword"));
           setLoading(false);
+          await refreshLoginStatus();
         }
       }
     } catch (err) {
-      setError(t("login.loginFailed") + ": " + err);
+      setError(t("login.loginFailed") + ": " + errorMessage(t, err));
       setLoading(false);
+      // 失败次数达到上限时保险库可能已被清除
+      await refreshLoginStatus();
+      await checkMasterPassword();
     }
   };
 
@@ -64,7 +167,42 @@ function Login({ onLogin }: LoginProps) {
       <div className="login-box">
         <div className="login-header">
           <h1>🔐 2Pass</h1>
-          <p>{isCreating ? t("login.createMasterPassword") : t("login.title")}</p>
+          <p>{creating ? t("login.createMasterPassword") : t("login.title")}</p>
+        </div>
+
+        <div className="vault-picker">
+          {newVaultPath ? (
+            <span className="vault-new-path" title={newVaultPath}>
+              {t("login.newVaultAt", { path: newVaultPath })}
+            </span>
+          ) : (
+            <select
+              value={vaults.find((v) => v.active)?.id ?? ""}
+              onChange={(e) => handleSwitchVault(e.target.value)}
+              title={vaults.find((v) => v.active)?.path}
+            >
+              {vaults.map((vault) => (
+                <option key={vault.id} value={vault.id}>
+                  {vault.unlocked ? "🔓 " : "🔒 "}
+                  {vault.name}
+                </option>
+              ))}
+            </select>
+          )}
+          {newVaultPath ? (
+            <button type="button" onClick={() => setNewVaultPath(null)}>
+              {t("forms.cancel")}
+            </button>
+          ) : (
+            <>
+              <button type="button" onClick={handleOpenVault}>
+                {t("login.openVault")}
+              </button>
+              <button type="button" onClick={handleNewVault}>
+                {t("login.newVault")}
+              </button>
+            </>
+          )}
         </div>
 
         <form onSubmit={handleSubmit} className="login-form">
@@ -81,7 +219,7 @@ function Login({ onLogin }: LoginProps) {
             />
           </div>
 
-          {isCreating && (
+          {creating && (
             <div className="form-group">
               <label htmlFor="confirm-password">{t("changeMasterPassword.confirmPassword")}</label>
               <input
@@ -95,15 +233,65 @@ function Login({ onLogin }: LoginProps) {
             </div>
           )}
 
+          <div className="form-group">
+            <label>{t("login.keyFile")}</label>
+            <div className="key-file-picker">
+              <span className="key-file-name">{keyFilePath ?? t("login.noKeyFile")}</span>
+              {keyFilePath ? (
+                <button type="button" onClick={() => setKeyFilePath(null)}>
+                  {t("forms.delete")}
+                </button>
+              ) : (
+                <button type="button" onClick={handleSelectKeyFile}>
+                  {t("login.selectKeyFile")}
+                </button>
+              )}
+            </div>
+          </div>
+
+          {!creating && (
+            <label className="read-only-option">
+              <input
+                type="checkbox"
+                checked={readOnly}
+                onChange={(e) => setReadOnly(e.target.checked)}
+              />
+              {t("login.openReadOnly")}
+            </label>
+          )}
+
           {error && <div className="error-message">{error}</div>}
 
-          <button type="submit" disabled={loading} className={`login-button ${loading ? 'loading' : ''}`}>
+          {!creating && loginStatus && loginStatus.failed_attempts > 0 && (
+            <div className="login-attempts">
+              {t("login.failedAttempts", {
+                count: loginStatus.failed_attempts,
+                time: loginStatus.last_failure_at ? new Date(loginStatus.last_failure_at).toLocaleString() : "",
+              })}
+              {loginStatus.wipe_after_failures > 0 &&
+                " " + t("login.wipeWarning", { remaining: loginStatus.wipe_after_failures - loginStatus.failed_attempts })}
+            </div>
+          )}
+
+          <button
+            type="submit"
+            disabled={loading || (!creating && retrySeconds > 0)}
+            className={`login-button ${loading ? 'loading' : ''}`}
+          >
             {loading && <span className="button-spinner"></span>}
-            <span>{loading ? t("common.loading") : isCreating ? t("forms.add") : t("login.unlock")}</span>
+            <span>
+              {loading
+                ? t("common.loading")
+                : creating
+                  ? t("forms.add")
+                  : retrySeconds > 0
+                    ? t("login.retryIn", { seconds: retrySeconds })
+                    : t("login.unlock")}
+            </span>
           </button>
         </form>
 
-        {isCreating && (
+        {creating && (
           <div className="info-box">
             <p>⚠️ {t("login.rememberPassword")}</p>
           </div>
@@ -55,6 +55,7 @@ function PasswordForm({ entry, groups, selectedGroupId, onSave, onCancel }: Pass
       tags: tags.length > 0 ? tags : undefined,
       group_id: groupId,
       sort_order: entry?.sort_order, // 保留原有排序
+      custom_fields: entry?.custom_fields, // 表单不编辑自定义字段，原样保留
       created_at: entry?.created_at || now,
       updated_at: now,
     };
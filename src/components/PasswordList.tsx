@@ -138,6 +138,17 @@ function SortablePasswordCard({
     setInitialMousePos(null);
   };
 
+  // 复制字段的 2pass:// 引用，可用于 2pass run / inject 和本机 API
+  const copyReference = async (field: string, key: string) => {
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const reference = await invoke<string>("get_entry_reference", { id: entry.id, field });
+      await onCopyToClipboard(reference, key);
+    } catch (err) {
+      console.error("❌ Failed to get reference:", err);
+    }
+  };
+
   return (
     <div
       ref={setNodeRef}
@@ -223,6 +234,18 @@ function SortablePasswordCard({
           >
             {copiedId === `quick-${entry.id}` ? "✓" : "🔑"}
           </button>
+          {/* 复制密码的引用 */}
+          <button
+            onClick={async (e) => {
+              e.preventDefault();
+              e.stopPropagation();
+              await copyReference("password", `ref-${entry.id}`);
+            }}
+            className={`action-btn copy-ref-btn ${copiedId === `ref-${entry.id}` ? 'copied' : ''}`}
+            title={t("passwords.copyReference")}
+          >
+            {copiedId === `ref-${entry.id}` ? "✓" : "🔗"}
+          </button>
           <button
             onClick={(e) => {
               e.preventDefault();
@@ -323,6 +346,38 @@ function SortablePasswordCard({
             </div>
           </div>
 
+          {/* 自定义字段 */}
+          {entry.custom_fields?.map((field) => (
+            <div className="info-row" key={field.name}>
+              <span className="info-label">🏷️ {field.name}</span>
+              <div className="info-value-group">
+                <span className="info-value password-value">
+                  {showPassword === entry.id ? field.value : "••••••••"}
+                </span>
+                <button
+                  onClick={async (e) => {
+                    e.stopPropagation();
+                    await onCopyToClipboard(field.value, `field-${entry.id}-${field.name}`);
+                  }}
+                  className={`icon-btn ${copiedId === `field-${entry.id}-${field.name}` ? 'copied' : ''}`}
+                  title={t("passwords.copyField")}
+                >
+                  {copiedId === `field-${entry.id}-${field.name}` ? "✓" : "📋"}
+                </button>
+                <button
+                  onClick={async (e) => {
+                    e.stopPropagation();
+                    await copyReference(`fields/${field.name}`, `field-ref-${entry.id}-${field.name}`);
+                  }}
+                  className={`icon-btn ${copiedId === `field-ref-${entry.id}-${field.name}` ? 'copied' : ''}`}
+                  title={t("passwords.copyReference")}
+                >
+                  {copiedId === `field-ref-${entry.id}-${field.name}` ? "✓" : "🔗"}
+                </button>
+              </div>
+            </div>
+          ))}
+
           {/* 备注 */}
           {entry.notes && (
             <div className="info-row notes-row">
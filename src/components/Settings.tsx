@@ -1,7 +1,10 @@
-import { useState } from "react";
+import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useTranslation } from "react-i18next";
 import ImportDialog from "./ImportDialog";
+import { ApiInfo, ApiClient, DataDirInfo, PasswordGroup, VaultInfo } from "../types";
+import { describePermissions } from "../utils/api";
+import { errorMessage, isCommandError } from "../utils/errors";
 import "../styles/Settings.css";
 
 interface SettingsProps {
@@ -22,6 +25,41 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
   const [confirmPassword, setConfirmPassword] = useState("");
   const [error, setError] = useState("");
   const [success, setSuccess] = useState("");
+  const [backupRetention, setBackupRetention] = useState(5);
+  const [wipeAfterFailures, setWipeAfterFailures] = useState(0);
+  // 用恢复密钥或密钥文件解锁时，可以不输入旧密码直接重设主密码
+  const [canResetPassword, setCanResetPassword] = useState(false);
+  const [activeVault, setActiveVault] = useState<VaultInfo | null>(null);
+  const [dataDir, setDataDir] = useState<DataDirInfo | null>(null);
+  const [apiInfo, setApiInfo] = useState<ApiInfo | null>(null);
+  const [groups, setGroups] = useState<PasswordGroup[]>([]);
+
+  const loadApiInfo = () =>
+    invoke<ApiInfo>("get_api_info")
+      .then(setApiInfo)
+      .catch((err) => console.error("Failed to load API clients:", err));
+
+  useEffect(() => {
+    invoke<{ backup_retention: number; wipe_after_failures: number }>("get_settings")
+      .then((settings) => {
+        setBackupRetention(settings.backup_retention);
+        setWipeAfterFailures(settings.wipe_after_failures);
+      })
+      .catch((err) => console.error("Failed to load settings:", err));
+    invoke<string | null>("get_unlock_method")
+      .then((method) => setCanResetPassword(["recovery_key", "key_file", "shares"].includes(method ?? "")))
+      .catch((err) => console.error("Failed to load unlock method:", err));
+    invoke<VaultInfo>("get_active_vault")
+      .then(setActiveVault)
+      .catch((err) => console.error("Failed to load active vault:", err));
+    invoke<DataDirInfo>("get_data_dir")
+      .then(setDataDir)
+      .catch((err) => console.error("Failed to load data directory:", err));
+    invoke<PasswordGroup[]>("get_all_groups")
+      .then(setGroups)
+      .catch((err) => console.error("Failed to load groups:", err));
+    loadApiInfo();
+  }, []);
 
   const autoLockOptions = [
     { value: 0, label: t("settings.autoLockOptions.disabled") },
@@ -42,6 +80,21 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
     { value: "glass", label: t("settings.themes.glass"), preview: "linear-gradient(135deg, #a5b4fc 0%, #93c5fd 100%)" },
   ];
 
+  const backupRetentionOptions = [
+    { value: 0, label: t("settings.backupRetentionOff") },
+    { value: 3, label: "3" },
+    { value: 5, label: "5" },
+    { value: 10, label: "10" },
+    { value: 20, label: "20" },
+  ];
+
+  const wipeAfterOptions = [
+    { value: 0, label: t("settings.wipeAfterFailuresOff") },
+    { value: 5, label: "5" },
+    { value: 10, label: "10" },
+    { value: 20, label: "20" },
+  ];
+
   const languageOptions = [
     { value: "zh-CN", label: t("settings.languages.zh-CN") },
     { value: "en-US", label: t("settings.languages.en-US") },
@@ -79,7 +132,60 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
       alert("✓ " + t("settings.exportData") + " " + t("common.success") + "！" + t("common.info") + "：\n" + filePath);
     } catch (err) {
       console.error(t("settings.exportData") + " " + t("common.error") + ":", err);
-      alert(t("settings.exportData") + " " + t("common.error") + "：" + err);
+      alert(t("settings.exportData") + " " + t("common.error") + "：" + errorMessage(t, err));
+    }
+  };
+
+  const handleGenerateEmergencyKit = async () => {
+    try {
+      const { save } = await import("@tauri-apps/plugin-dialog");
+      const { writeTextFile } = await import("@tauri-apps/plugin-fs");
+
+      const filePath = await save({
+        defaultPath: `2pass-emergency-kit-${new Date().toISOString().split("T")[0]}.html`,
+        filters: [{
+          name: "HTML",
+          extensions: ["html"]
+        }]
+      });
+
+      if (!filePath) {
+        return;
+      }
+
+      // 恢复密钥在用户确认保存位置后才生成，避免登记用不上的 key slot
+      const kit = await invoke<{ html: string }>("generate_emergency_kit");
+      await writeTextFile(filePath, kit.html);
+
+      alert("✓ " + t("settings.emergencyKitSaved") + "\n" + filePath);
+    } catch (err) {
+      console.error(t("settings.emergencyKit") + " " + t("common.error") + ":", err);
+      alert(t("settings.emergencyKit") + " " + t("common.error") + "：" + errorMessage(t, err));
+    }
+  };
+
+  const handleWipeAfterFailuresChange = async (count: number) => {
+    if (count > 0 && !confirm(t("settings.wipeAfterFailuresConfirm", { count }))) {
+      return;
+    }
+    try {
+      const settings = await invoke<Record<string, unknown>>("get_settings");
+      await invoke("update_settings", { settings: { ...settings, wipe_after_failures: count } });
+      setWipeAfterFailures(count);
+    } catch (err) {
+      console.error("Failed to update settings:", err);
+    }
+  };
+
+  const handleRevokeApiClient = async (client: ApiClient) => {
+    if (!confirm(t("settings.apiRevokeConfirm", { name: client.name }))) {
+      return;
+    }
+    try {
+      await invoke("revoke_api_client", { id: client.id });
+      await loadApiInfo();
+    } catch (err) {
+      alert(errorMessage(t, err));
     }
   };
 
@@ -89,6 +195,16 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
     // alert("✓ 导入成功！请刷新页面查看导入的密码");
   };
 
+  const handleBackupRetentionChange = async (count: number) => {
+    try {
+      const settings = await invoke<Record<string, unknown>>("get_settings");
+      await invoke("update_settings", { settings: { ...settings, backup_retention: count } });
+      setBackupRetention(count);
+    } catch (err) {
+      console.error("Failed to update settings:", err);
+    }
+  };
+
   const handleLanguageChange = (language: string) => {
     i18n.changeLanguage(language);
     localStorage.setItem('language', language);
@@ -98,7 +214,7 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
     setError("");
     setSuccess("");
 
-    if (!oldPassword || !newPassword || !confirmPassword) {
+    if ((!oldPassword && !canResetPassword) || !newPassword || !confirmPassword) {
       setError(t("changeMasterPassword.allFieldsRequired"));
       return;
     }
@@ -114,10 +230,14 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
     }
 
     try {
-      await invoke("change_master_password", {
-        oldPassword: oldPassword,
-        newPassword: newPassword,
-      });
+      if (canResetPassword && !oldPassword) {
+        await invoke("reset_master_password", { newPassword });
+      } else {
+        await invoke("change_master_password", {
+          oldPassword: oldPassword,
+          newPassword: newPassword,
+        });
+      }
       setSuccess(t("changeMasterPassword.success"));
       setOldPassword("");
       setNewPassword("");
@@ -127,7 +247,11 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
         onLock();
       }, 2000);
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(t, err));
+      // 旧密码错误次数过多时保险库可能已被清除，回到登录界面
+      if (isCommandError(err) && err.code === "VaultWiped") {
+        setTimeout(onLock, 2000);
+      }
     }
   };
   return (
@@ -198,6 +322,16 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
             </button>
           </div>
 
+          <div className="setting-item">
+            <div className="setting-info">
+              <h3>{t("settings.emergencyKit")}</h3>
+              <p>{t("settings.emergencyKitDescription")}</p>
+            </div>
+            <button className="setting-action-btn" onClick={handleGenerateEmergencyKit}>
+              🧰 {t("settings.generateEmergencyKit")}
+            </button>
+          </div>
+
           <div className="setting-item full-width">
             <div className="setting-info">
               <h3>{t("settings.autoLock")}</h3>
@@ -215,11 +349,90 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
               ))}
             </div>
           </div>
+
+          <div className="setting-item full-width">
+            <div className="setting-info">
+              <h3>{t("settings.wipeAfterFailures")}</h3>
+              <p>{t("settings.wipeAfterFailuresDescription")}</p>
+            </div>
+            <div className="time-selector">
+              {wipeAfterOptions.map((option) => (
+                <button
+                  key={option.value}
+                  className={`time-option ${wipeAfterFailures === option.value ? "active" : ""}`}
+                  onClick={() => handleWipeAfterFailuresChange(option.value)}
+                >
+                  {option.label}
+                </button>
+              ))}
+            </div>
+          </div>
         </div>
 
+        {apiInfo && (
+          <div className="settings-section">
+            <h2>🔌 {t("settings.apiAccess")}</h2>
+            <div className="setting-item">
+              <div className="setting-info">
+                <h3>{t("settings.apiSocket", { version: apiInfo.version })}</h3>
+                <p title={apiInfo.socket ?? undefined}>
+                  {apiInfo.socket ?? t("settings.apiUnsupported")}
+                </p>
+              </div>
+            </div>
+            {apiInfo.clients.length === 0 ? (
+              <div className="setting-item">
+                <div className="setting-info">
+                  <p>{t("settings.apiNoClients")}</p>
+                </div>
+              </div>
+            ) : (
+              apiInfo.clients.map((client) => (
+                <div className="setting-item" key={client.id}>
+                  <div className="setting-info">
+                    <h3>{client.name}</h3>
+                    <p>
+                      {describePermissions(t, client.permissions, groups)} ·{" "}
+                      {client.last_used_at
+                        ? t("settings.apiLastUsed", { time: new Date(client.last_used_at).toLocaleString() })
+                        : t("settings.apiNeverUsed")}
+                    </p>
+                  </div>
+                  <button className="setting-action-btn danger-btn" onClick={() => handleRevokeApiClient(client)}>
+                    {t("settings.apiRevoke")}
+                  </button>
+                </div>
+              ))
+            )}
+          </div>
+        )}
 
         <div className="settings-section">
           <h2>💾 {t("settings.dataManagement")}</h2>
+          {activeVault && (
+            <div className="setting-item">
+              <div className="setting-info">
+                <h3>{t("settings.currentVault", { name: activeVault.name })}</h3>
+                <p title={activeVault.path}>{activeVault.path}</p>
+              </div>
+              {/* 锁定后在解锁界面选择、打开或新建其它保险库 */}
+              <button className="setting-action-btn" onClick={onLock}>
+                🗄️ {t("settings.switchVault")}
+              </button>
+            </div>
+          )}
+
+          {dataDir && (
+            <div className="setting-item">
+              <div className="setting-info">
+                <h3>{t("settings.dataDirectory")}</h3>
+                <p title={dataDir.path}>
+                  {dataDir.path} ({t(`settings.dataDirectorySource.${dataDir.source}`)})
+                </p>
+              </div>
+            </div>
+          )}
+
           <div className="setting-item">
             <div className="setting-info">
               <h3>{t("settings.exportData")}</h3>
@@ -242,6 +455,24 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
               📥 {t("settings.import")}
             </button>
           </div>
+
+          <div className="setting-item full-width">
+            <div className="setting-info">
+              <h3>{t("settings.backupRetention")}</h3>
+              <p>{t("settings.backupRetentionDescription")}</p>
+            </div>
+            <div className="time-selector">
+              {backupRetentionOptions.map((option) => (
+                <button
+                  key={option.value}
+                  className={`time-option ${backupRetention === option.value ? "active" : ""}`}
+                  onClick={() => handleBackupRetentionChange(option.value)}
+                >
+                  {option.label}
+                </button>
+              ))}
+            </div>
+          </div>
         </div>
 
         <div className="settings-section danger-section">
@@ -288,7 +519,11 @@ function Settings({ autoLockTimeout, onAutoLockChange, onLock, theme, onThemeCha
                   type="password"
                   value={oldPassword}
                   onChange={(e) => setOldPassword(e.target.value)}
-                  placeholder={t("changeMasterPassword.currentPasswordPlaceholder")}
+                  placeholder={
+                    canResetPassword
+                      ? t("changeMasterPassword.currentPasswordOptional")
+                      : t("changeMasterPassword.currentPasswordPlaceholder")
+                  }
                   autoFocus
                 />
               </div>
@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useTranslation } from "react-i18next";
+import { errorMessage } from "../utils/errors";
 import "../styles/TotpConfig.css";
 
 interface TotpConfigProps {
@@ -73,7 +74,7 @@ function TotpConfig({
       console.log("✓ Secret validated and set successfully");
     } catch (error) {
       console.error("Failed to generate secret:", error);
-      setTestError(`${t("totp.generateSecretFailed")}: ${error}`);
+      setTestError(`${t("totp.generateSecretFailed")}: ${errorMessage(t, error)}`);
     }
   };
 
@@ -124,7 +125,7 @@ function TotpConfig({
     } catch (error) {
       console.error("Failed to generate TOTP:", error);
       console.error("Secret that failed:", secret);
-      setTestError(`${t("totp.generateFailed")}: ${error}`);
+      setTestError(`${t("totp.generateFailed")}: ${errorMessage(t, error)}`);
       setTestCode("");
     } finally {
       setIsGeneratingTest(false);
@@ -2,6 +2,7 @@ import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useTranslation } from "react-i18next";
 import { useCopy } from "../hooks/useCopy";
+import { errorMessage } from "../utils/errors";
 import "../styles/TotpDisplay.css";
 
 interface TotpDisplayProps {
@@ -27,7 +28,7 @@ function TotpDisplay({ secret, password, onCopy }: TotpDisplayProps) {
       } catch (err) {
         console.error("Failed to generate TOTP:", err);
         setCode("ERROR");
-        setError(String(err));
+        setError(errorMessage(t, err));
       }
     };
 
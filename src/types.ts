@@ -1,3 +1,5 @@
+import type { CommandError } from "./utils/errors";
+
 export interface PasswordHistory {
   timestamp: number;
   password?: string;
@@ -5,6 +7,11 @@ export interface PasswordHistory {
   notes?: string;
 }
 
+export interface CustomField {
+  name: string;
+  value: string;
+}
+
 export interface PasswordEntry {
   id: string;
   title: string;
@@ -19,6 +26,7 @@ export interface PasswordEntry {
   created_at: number;
   updated_at: number;
   history?: PasswordHistory[] | undefined; // 修改历史
+  custom_fields?: CustomField[] | null; // 自定义字段，可用 2pass://.../fields/<名称> 引用
 }
 
 export interface PasswordGroup {
@@ -30,6 +38,21 @@ export interface PasswordGroup {
   created_at: number;
 }
 
+export interface VaultInfo {
+  id: string;
+  name: string;
+  path: string;
+  active: boolean;
+  unlocked: boolean;
+  read_only: boolean; // 以只读方式打开
+  exists: boolean; // 保险库文件是否存在
+}
+
+export interface DataDirInfo {
+  path: string;
+  source: "argument" | "environment" | "portable" | "app_data";
+}
+
 export interface LoginProps {
   onLogin: () => Promise<void>;
 }
@@ -53,3 +76,48 @@ export interface PasswordFormProps {
   onCancel: () => void;
 }
 
+
+// 本机 API 令牌的权限范围
+export interface ApiPermissions {
+  read_only: boolean;
+  groups?: string[] | null; // 限定的分组 id，为空表示不限
+}
+
+export interface ApiClient {
+  id: string;
+  name: string;
+  permissions: ApiPermissions;
+  created_at: number;
+  last_used_at?: number | null;
+}
+
+export interface ApiTokenRequest {
+  id: string;
+  name: string;
+  permissions: ApiPermissions;
+  requested_at: number;
+}
+
+export interface ApiInfo {
+  version: number;
+  socket: string | null; // 不支持 Unix socket 的平台为 null
+  clients: ApiClient[];
+}
+
+// 模板中的一个 2pass:// 引用及其对应的条目（preview_template），不含字段的值
+export interface TemplateReference {
+  reference: string;
+  line: number;
+  entry_id: string | null;
+  entry_title: string | null;
+  field: string | null;
+  error: CommandError | null;
+}
+
+// resolve_reference 的结果
+export interface ResolvedReference {
+  vault_id: string;
+  entry_id: string;
+  entry_title: string;
+  value: string;
+}
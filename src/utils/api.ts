@@ -0,0 +1,17 @@
+// 本机 API 令牌的权限说明，用于批准对话框和设置页面
+import type { TFunction } from "i18next";
+import { ApiPermissions, PasswordGroup } from "../types";
+
+export const describePermissions = (
+  t: TFunction,
+  permissions: ApiPermissions,
+  groups: PasswordGroup[]
+): string => {
+  const access = permissions.read_only ? t("api.readOnly") : t("api.readWrite");
+  if (!permissions.groups) {
+    return `${access} · ${t("api.allGroups")}`;
+  }
+  // 分组已被删除时显示 id
+  const names = permissions.groups.map((id) => groups.find((g) => g.id === id)?.name ?? id);
+  return `${access} · ${t("api.groups", { groups: names.join(", ") })}`;
+};
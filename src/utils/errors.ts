@@ -0,0 +1,16 @@
+// 后端命令返回的错误：{ code, details }，code 是稳定的错误码，按 code 显示本地化的提示
+import type { TFunction } from "i18next";
+
+export interface CommandError {
+  code: string;
+  details?: Record<string, unknown>;
+}
+
+export const isCommandError = (err: unknown): err is CommandError =>
+  typeof err === "object" && err !== null && typeof (err as CommandError).code === "string";
+
+// 未知的错误码显示错误码本身，非命令错误（前端异常等）直接转成字符串
+export const errorMessage = (t: TFunction, err: unknown): string => {
+  if (!isCommandError(err)) return String(err);
+  return t(`errors.${err.code}`, { ...err.details, defaultValue: err.code });
+};